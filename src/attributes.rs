@@ -11,11 +11,9 @@ pub mod command;
 pub mod event;
 pub mod notification;
 
-use nom::{
-    multi::count,
-    number::complete::{le_u16, le_u8},
-    IResult,
-};
+use crate::{parse, Error, ParseOptions};
+
+use nom::IResult;
 use std::fmt::Debug;
 
 use self::{notification::NotificationAttributeID, app::AppAttributeID};
@@ -28,7 +26,9 @@ pub struct NotificationAttribute {
     pub value: Option<String>
 }
 
-impl From<NotificationAttribute> for Vec<u8> {
+impl TryFrom<NotificationAttribute> for Vec<u8> {
+    type Error = Error;
+
     /// Converts a `NotificationAttribute` to a `Vec<u8>`:
     /// 
     /// # Examples
@@ -44,7 +44,7 @@ impl From<NotificationAttribute> for Vec<u8> {
     ///    value: Some(attribute_data)
     /// };
     /// 
-    /// let converted_bytes: Vec<u8> = attribute.into();
+    /// let converted_bytes: Vec<u8> = attribute.try_into().unwrap();
     /// 
     /// assert_eq!(u8::MIN, converted_bytes[0]); // Identifier for attribute
     /// assert_eq!(4, converted_bytes[1]); // Length of attribute
@@ -54,26 +54,22 @@ impl From<NotificationAttribute> for Vec<u8> {
     /// assert_eq!(115, converted_bytes[5]); // s string char
     /// assert_eq!(116, converted_bytes[6]); // t string char strings are not NULL terminated so this is the end
     /// ```
-    fn from(original: NotificationAttribute) -> Vec<u8> {
-        let mut vec: Vec<u8> = Vec::new();
-
-        let id: u8 = original.id.into();
-        let length: [u8; 2] = original.length.to_le_bytes();
-        let attribute: Option<Vec<u8>> = match original.value {
-            Some(value) => { Some(value.into_bytes()) },
-            None => None,
-        };
-
-        vec.push(id);
-        vec.extend(length.to_vec());
-
-        // If the attribute's value isn't null we add it to our bytes.
-        match attribute {
-            Some(attribute) => { vec.extend(attribute)},
-            None => (),
-        };
-
-        return vec;
+    ///
+    /// A `length` that disagrees with the value is rejected:
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::NotificationAttribute;
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// let attribute: NotificationAttribute = NotificationAttribute {
+    ///    id: NotificationAttributeID::Title,
+    ///    length: 2,
+    ///    value: Some("test".to_string())
+    /// };
+    ///
+    /// assert_eq!(Vec::<u8>::try_from(attribute), Err(Error::LengthMismatch { expected: 2, actual: 4 }));
+    /// ```
+    fn try_from(original: NotificationAttribute) -> Result<Vec<u8>, Self::Error> {
+        encode_attribute(original.id.into(), original.length, original.value)
     }
 }
 
@@ -99,18 +95,56 @@ impl NotificationAttribute {
     /// assert_eq!(bytes.len(), 1);
     /// assert_eq!(bytes, [0]);
     /// ```
+    ///
+    /// Values that are not valid UTF-8 are reported instead of panicking:
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::NotificationAttribute;
+    /// let bytes: Vec<u8> = vec![1, 2, 0, 0xC3, 0x28];
+    ///
+    /// assert_eq!(NotificationAttribute::parse(&bytes), Err(nom::Err::Failure(Error::InvalidUtf8)));
+    /// ```
     /// 
-    pub fn parse(i: &[u8]) -> IResult<&[u8], NotificationAttribute> {
-        let (i, id) = notification::NotificationAttributeID::parse(i)?;
-        let (i, length) = le_u16(i)?;
-        let (i, attribute) = count(le_u8, length.into())(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], NotificationAttribute, Error> {
+        NotificationAttribute::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `NotificationAttribute` from a `&[u8]` that may not hold the whole attribute yet
+    ///
+    /// When the input ends early the parser returns `nom::Err::Incomplete` with the
+    /// number of bytes still needed, so data can be fed in as it arrives.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::NotificationAttribute;
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// let bytes: Vec<u8> = vec![0, 4, 0, 116, 101];
+    ///
+    /// assert_eq!(NotificationAttribute::parse_streaming(&bytes[..2]), Err(nom::Err::Incomplete(nom::Needed::new(1))));
+    /// assert_eq!(NotificationAttribute::parse_streaming(&bytes), Err(nom::Err::Incomplete(nom::Needed::new(2))));
+    ///
+    /// let bytes: Vec<u8> = vec![0, 4, 0, 116, 101, 115, 116];
+    /// let (_, attribute) = NotificationAttribute::parse_streaming(&bytes).unwrap();
+    ///
+    /// assert_eq!(attribute.id, NotificationAttributeID::AppIdentifier);
+    /// assert_eq!(attribute.value, Some("test".to_string()));
+    /// ```
+    pub fn parse_streaming(i: &[u8]) -> IResult<&[u8], NotificationAttribute, Error> {
+        NotificationAttribute::parse_with(i, ParseOptions::default().streaming())
+    }
+
+    /// Attempts to parse a `NotificationAttribute` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], NotificationAttribute, Error> {
+        let (i, id) = notification::NotificationAttributeID::parse_with(i, options)?;
+        let (i, length) = parse::le_u16(i, options)?;
+        let (i, value) = parse_value(i, length, options)?;
 
         Ok((
             i,
             NotificationAttribute {
-                id: notification::NotificationAttributeID::try_from(id).unwrap(),
-                length: length,
-                value: Some(String::from_utf8(attribute).unwrap()),
+                id,
+                length,
+                value: Some(value),
             },
         ))
     }
@@ -124,7 +158,9 @@ pub struct AppAttribute {
     pub value: Option<String>
 }
 
-impl From<AppAttribute> for Vec<u8> {
+impl TryFrom<AppAttribute> for Vec<u8> {
+    type Error = Error;
+
     /// Converts a `AppAttribute` to a `Vec<u8>`:
     /// 
     /// # Examples
@@ -140,7 +176,7 @@ impl From<AppAttribute> for Vec<u8> {
     ///    value: Some(attribute_data)
     /// };
     /// 
-    /// let converted_bytes: Vec<u8> = attribute.into();
+    /// let converted_bytes: Vec<u8> = attribute.try_into().unwrap();
     /// 
     /// assert_eq!(u8::MIN, converted_bytes[0]); // Identifier for attribute
     /// assert_eq!(4, converted_bytes[1]); // Length of attribute
@@ -150,26 +186,8 @@ impl From<AppAttribute> for Vec<u8> {
     /// assert_eq!(115, converted_bytes[5]); // s string char
     /// assert_eq!(116, converted_bytes[6]); // t string char strings are not NULL terminated so this is the end
     /// ```
-    fn from(original: AppAttribute) -> Vec<u8> {
-        let mut vec: Vec<u8> = Vec::new();
-
-        let id: u8 = original.id.into();
-        let length: [u8; 2] = original.length.to_le_bytes();
-        let attribute: Option<Vec<u8>> = match original.value {
-            Some(value) => { Some(value.into_bytes()) },
-            None => None,
-        };
-
-        vec.push(id);
-        vec.extend(length.to_vec());
-
-        // If the attribute's value isn't null we add it to our bytes.
-        match attribute {
-            Some(attribute) => { vec.extend(attribute)},
-            None => (),
-        };
-
-        return vec;
+    fn try_from(original: AppAttribute) -> Result<Vec<u8>, Self::Error> {
+        encode_attribute(original.id.into(), original.length, original.value)
     }
 }
 
@@ -196,18 +214,78 @@ impl AppAttribute {
     /// assert_eq!(bytes, [0]);
     /// ```
     /// 
-    pub fn parse(i: &[u8]) -> IResult<&[u8], AppAttribute> {
-        let (i, id) = app::AppAttributeID::parse(i)?;
-        let (i, length) = le_u16(i)?;
-        let (i, attribute) = count(le_u8, length.into())(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], AppAttribute, Error> {
+        AppAttribute::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `AppAttribute` from a `&[u8]` that may not hold the whole attribute yet
+    ///
+    /// When the input ends early the parser returns `nom::Err::Incomplete` with the
+    /// number of bytes still needed, so data can be fed in as it arrives.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::AppAttribute;
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// let bytes: Vec<u8> = vec![0, 4, 0, 116, 101];
+    ///
+    /// assert_eq!(AppAttribute::parse_streaming(&bytes[..2]), Err(nom::Err::Incomplete(nom::Needed::new(1))));
+    /// assert_eq!(AppAttribute::parse_streaming(&bytes), Err(nom::Err::Incomplete(nom::Needed::new(2))));
+    ///
+    /// let bytes: Vec<u8> = vec![0, 4, 0, 116, 101, 115, 116];
+    /// let (_, attribute) = AppAttribute::parse_streaming(&bytes).unwrap();
+    ///
+    /// assert_eq!(attribute.id, AppAttributeID::DisplayName);
+    /// assert_eq!(attribute.value, Some("test".to_string()));
+    /// ```
+    pub fn parse_streaming(i: &[u8]) -> IResult<&[u8], AppAttribute, Error> {
+        AppAttribute::parse_with(i, ParseOptions::default().streaming())
+    }
+
+    /// Attempts to parse a `AppAttribute` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], AppAttribute, Error> {
+        let (i, id) = app::AppAttributeID::parse_with(i, options)?;
+        let (i, length) = parse::le_u16(i, options)?;
+        let (i, value) = parse_value(i, length, options)?;
 
         Ok((
             i,
             AppAttribute {
-                id: app::AppAttributeID::try_from(id).unwrap(),
-                length: length,
-                value: Some(String::from_utf8(attribute).unwrap()),
+                id,
+                length,
+                value: Some(value),
             },
         ))
     }
 }
+
+/// Encodes the shared wire format of `NotificationAttribute` and `AppAttribute`,
+/// validating that the declared length matches the value.
+fn encode_attribute(id: u8, length: u16, value: Option<String>) -> Result<Vec<u8>, Error> {
+    let value: Vec<u8> = value.map(|value| value.into_bytes()).unwrap_or_default();
+
+    if value.len() != usize::from(length) {
+        return Err(Error::LengthMismatch {
+            expected: length.into(),
+            actual: value.len(),
+        });
+    }
+
+    let mut vec: Vec<u8> = Vec::with_capacity(3 + value.len());
+
+    vec.push(id);
+    vec.extend(length.to_le_bytes());
+    vec.extend(value);
+
+    Ok(vec)
+}
+
+/// Parses an attribute value of `length` bytes as a UTF-8 string.
+fn parse_value(i: &[u8], length: u16, options: ParseOptions) -> IResult<&[u8], String, Error> {
+    let (i, value) = parse::take(i, length.into(), options)?;
+
+    match String::from_utf8(value.to_vec()) {
+        Ok(value) => Ok((i, value)),
+        Err(_) => Err(nom::Err::Failure(Error::InvalidUtf8)),
+    }
+}
@@ -1,9 +1,17 @@
-use nom::{error::ParseError, number::complete::le_u8, IResult};
+use crate::{parse, Error, ParseOptions};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use nom::IResult;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ActionID {
-    Positive = 0,
-    Negative = 1,
+    Positive,
+    Negative,
+    /// An identifier not defined by the ANCS specification, kept as received.
+    /// It never holds the byte of a named variant: parsers and `from_u8` only
+    /// produce it for unassigned identifiers, and building one by hand for an
+    /// assigned identifier is a logic error.
+    Unknown(u8),
 }
 
 impl From<ActionID> for u8 {
@@ -20,12 +28,13 @@ impl From<ActionID> for u8 {
         match original {
             ActionID::Positive => 0,
             ActionID::Negative => 1,
+            ActionID::Unknown(id) => id,
         }
     }
 }
 
 impl TryFrom<u8> for ActionID {
-    type Error = ActionIDError;
+    type Error = Error;
 
     /// Attempts to convert a `u8` to a `ActionID`
     ///
@@ -41,12 +50,31 @@ impl TryFrom<u8> for ActionID {
         match original {
             0 => Ok(ActionID::Positive),
             1 => Ok(ActionID::Negative),
-            _ => Err(ActionIDError),
+            _ => Err(Error::InvalidActionID(original)),
         }
     }
 }
 
 impl ActionID {
+    /// Converts a `u8` to a `ActionID`, keeping identifiers not defined by the ANCS
+    /// specification as `ActionID::Unknown`. Assigned identifiers always map to their
+    /// named variant, so values built this way compare equal exactly when they
+    /// encode to the same byte.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::action::ActionID;
+    /// assert_eq!(ActionID::from_u8(0), ActionID::Positive);
+    /// assert_eq!(ActionID::from_u8(200), ActionID::Unknown(200));
+    ///
+    /// let (_, id) = ActionID::parse_with(&[0], ParseOptions::lenient()).unwrap();
+    /// assert_eq!(id, ActionID::Positive);
+    /// ```
+    pub fn from_u8(id: u8) -> ActionID {
+        ActionID::try_from(id).unwrap_or(ActionID::Unknown(id))
+    }
+
     /// Attempts to parse a `ActionID` from a `&[u8]`
     ///
     /// # Examples
@@ -58,18 +86,18 @@ impl ActionID {
     /// assert_eq!(ActionID::Positive, action_id);
     /// ```
     ///
-    pub fn parse(i: &[u8]) -> IResult<&[u8], ActionID> {
-        let (i, action_id) = le_u8(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], ActionID, Error> {
+        ActionID::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `ActionID` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], ActionID, Error> {
+        let (i, action_id) = parse::le_u8(i, options)?;
 
         match ActionID::try_from(action_id) {
             Ok(action_id) => Ok((i, action_id)),
-            Err(_) => Err(nom::Err::Failure(ParseError::from_error_kind(
-                i,
-                nom::error::ErrorKind::Fail,
-            ))),
+            Err(_) if options.allow_unknown_ids => Ok((i, ActionID::from_u8(action_id))),
+            Err(error) => Err(nom::Err::Failure(error)),
         }
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct ActionIDError;
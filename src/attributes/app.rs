@@ -1,14 +1,18 @@
-use nom::{
-    number::complete::{le_u8},
-    error::{ParseError},
-    IResult,
-};
+use crate::{parse, Error, ParseOptions};
+
+use nom::IResult;
 
 
 /// The `AppAttributeID` type. See [the module level documentation](index.html) for more.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AppAttributeID {
-    DisplayName = 0,
+    DisplayName,
+    /// An identifier not defined by the ANCS specification, kept as received.
+    /// It never holds the byte of a named variant: parsers and `from_u8` only
+    /// produce it for unassigned identifiers, and building one by hand for an
+    /// assigned identifier is a logic error.
+    Unknown(u8),
 }
 
 impl From<AppAttributeID> for u8 {
@@ -26,12 +30,13 @@ impl From<AppAttributeID> for u8 {
     fn from(original: AppAttributeID) -> u8 {
         match original {
             AppAttributeID::DisplayName => 0,
+            AppAttributeID::Unknown(id) => id,
         }
     }
 }
 
 impl TryFrom<u8> for AppAttributeID {
-    type Error = AttributeIDError;
+    type Error = Error;
 
     /// Attempts to convert a `u8` to a valid `AppAttributeID`
     /// 
@@ -46,12 +51,31 @@ impl TryFrom<u8> for AppAttributeID {
     fn try_from(original: u8) -> Result<Self, Self::Error> {
         match original {
             0 => Ok(AppAttributeID::DisplayName),
-            _ => Err(AttributeIDError)
+            _ => Err(Error::InvalidAppAttributeID(original)),
         }
     }
 }
 
 impl AppAttributeID {
+    /// Converts a `u8` to a `AppAttributeID`, keeping identifiers not defined by the ANCS
+    /// specification as `AppAttributeID::Unknown`. Assigned identifiers always map to their
+    /// named variant, so values built this way compare equal exactly when they
+    /// encode to the same byte.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// assert_eq!(AppAttributeID::from_u8(0), AppAttributeID::DisplayName);
+    /// assert_eq!(AppAttributeID::from_u8(200), AppAttributeID::Unknown(200));
+    ///
+    /// let (_, id) = AppAttributeID::parse_with(&[0], ParseOptions::lenient()).unwrap();
+    /// assert_eq!(id, AppAttributeID::DisplayName);
+    /// ```
+    pub fn from_u8(id: u8) -> AppAttributeID {
+        AppAttributeID::try_from(id).unwrap_or(AppAttributeID::Unknown(id))
+    }
+
     /// Attempts to parse a `AppAttributeID` from a `&[u8]`
     /// 
     /// # Examples
@@ -63,15 +87,18 @@ impl AppAttributeID {
     /// assert_eq!(AppAttributeID::DisplayName, app_attribute_id);
     /// ```
     /// 
-    pub fn parse(i: &[u8]) -> IResult<&[u8], AppAttributeID> {
-        let (i, app_attribute_id) = le_u8(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], AppAttributeID, Error> {
+        AppAttributeID::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `AppAttributeID` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], AppAttributeID, Error> {
+        let (i, app_attribute_id) = parse::le_u8(i, options)?;
 
         match AppAttributeID::try_from(app_attribute_id) {
-            Ok(app_attribute_id) => { Ok((i, app_attribute_id)) },
-            Err(_) => Err(nom::Err::Failure(ParseError::from_error_kind(i, nom::error::ErrorKind::Fail))),
-        } 
+            Ok(app_attribute_id) => Ok((i, app_attribute_id)),
+            Err(_) if options.allow_unknown_ids => Ok((i, AppAttributeID::from_u8(app_attribute_id))),
+            Err(error) => Err(nom::Err::Failure(error)),
+        }
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct AttributeIDError;
\ No newline at end of file
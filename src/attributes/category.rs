@@ -1,22 +1,27 @@
-use nom::{
-    number::complete::{le_u8},
-    IResult, error::ParseError,
-};
+use crate::{parse, Error, ParseOptions};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use nom::IResult;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CategoryID {
-    Other = 0,
-    IncomingCall = 1,
-    MissedCall = 2,
-    Voicemail = 3,
-    Social = 4,
-    Schedule = 5,
-    Email = 6,
-    News = 7,
-    HealthAndFitness = 8,
-    BusinessAndFinance = 9,
-    Location = 10,
-    Entertainment = 11,
+    Other,
+    IncomingCall,
+    MissedCall,
+    Voicemail,
+    Social,
+    Schedule,
+    Email,
+    News,
+    HealthAndFitness,
+    BusinessAndFinance,
+    Location,
+    Entertainment,
+    /// An identifier not defined by the ANCS specification, kept as received.
+    /// It never holds the byte of a named variant: parsers and `from_u8` only
+    /// produce it for unassigned identifiers, and building one by hand for an
+    /// assigned identifier is a logic error.
+    Unknown(u8),
 }
 
 impl From<CategoryID> for u8 {
@@ -45,12 +50,13 @@ impl From<CategoryID> for u8 {
             CategoryID::BusinessAndFinance => 9,
             CategoryID::Location => 10,
             CategoryID::Entertainment => 11,
+            CategoryID::Unknown(id) => id,
         }
     }
 }
 
 impl TryFrom<u8> for CategoryID {
-    type Error = CategoryIDError;
+    type Error = Error;
 
     /// Attempts to convert a u8 to a valid `CategoryID`
     /// 
@@ -77,12 +83,31 @@ impl TryFrom<u8> for CategoryID {
             9 => Ok(CategoryID::BusinessAndFinance),
             10 => Ok(CategoryID::Location),
             11 => Ok(CategoryID::Entertainment),
-            _ => Err(CategoryIDError),
+            _ => Err(Error::InvalidCategoryID(original)),
         }
     }
 }
 
 impl CategoryID {
+    /// Converts a `u8` to a `CategoryID`, keeping identifiers not defined by the ANCS
+    /// specification as `CategoryID::Unknown`. Assigned identifiers always map to their
+    /// named variant, so values built this way compare equal exactly when they
+    /// encode to the same byte.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::category::CategoryID;
+    /// assert_eq!(CategoryID::from_u8(0), CategoryID::Other);
+    /// assert_eq!(CategoryID::from_u8(200), CategoryID::Unknown(200));
+    ///
+    /// let (_, id) = CategoryID::parse_with(&[0], ParseOptions::lenient()).unwrap();
+    /// assert_eq!(id, CategoryID::Other);
+    /// ```
+    pub fn from_u8(id: u8) -> CategoryID {
+        CategoryID::try_from(id).unwrap_or(CategoryID::Unknown(id))
+    }
+
     /// Attempts to parse a `CategoryID` from a `&[u8]`
     /// 
     /// # Examples
@@ -94,15 +119,18 @@ impl CategoryID {
     /// assert_eq!(CategoryID::Other, category_id);
     /// ```
     /// 
-    pub fn parse(i: &[u8]) -> IResult<&[u8], CategoryID> {
-        let (i, category_id) = le_u8(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], CategoryID, Error> {
+        CategoryID::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `CategoryID` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], CategoryID, Error> {
+        let (i, category_id) = parse::le_u8(i, options)?;
 
         match CategoryID::try_from(category_id) {
-            Ok(category_id) => { Ok((i, category_id)) },
-            Err(_) => Err(nom::Err::Failure(ParseError::from_error_kind(i, nom::error::ErrorKind::Fail))),
+            Ok(category_id) => Ok((i, category_id)),
+            Err(_) if options.allow_unknown_ids => Ok((i, CategoryID::from_u8(category_id))),
+            Err(error) => Err(nom::Err::Failure(error)),
         }
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct CategoryIDError;
\ No newline at end of file
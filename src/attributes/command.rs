@@ -1,13 +1,18 @@
-use nom::{
-    number::complete::{le_u8},
-    IResult, error::ParseError,
-};
+use crate::{parse, Error, ParseOptions};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use nom::IResult;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CommandID {
-    GetNotificationAttributes = 0,
-    GetAppAttributes = 1,
-    PerformNotificationAction = 2,
+    GetNotificationAttributes,
+    GetAppAttributes,
+    PerformNotificationAction,
+    /// An identifier not defined by the ANCS specification, kept as received.
+    /// It never holds the byte of a named variant: parsers and `from_u8` only
+    /// produce it for unassigned identifiers, and building one by hand for an
+    /// assigned identifier is a logic error.
+    Unknown(u8),
 }
 
 impl From<CommandID> for u8 {
@@ -25,12 +30,13 @@ impl From<CommandID> for u8 {
             CommandID::GetNotificationAttributes => 0,
             CommandID::GetAppAttributes => 1,
             CommandID::PerformNotificationAction => 2,
+            CommandID::Unknown(id) => id,
         }
     }
 }
 
 impl TryFrom<u8> for CommandID {
-    type Error = CommandIDError;
+    type Error = Error;
     /// Attempts to convert a `u8` to a valid `CommandID`
     /// 
     /// # Examples
@@ -45,12 +51,31 @@ impl TryFrom<u8> for CommandID {
             0 => Ok(CommandID::GetNotificationAttributes),
             1 => Ok(CommandID::GetAppAttributes),
             2 => Ok(CommandID::PerformNotificationAction),
-            _ => Err(CommandIDError),
+            _ => Err(Error::InvalidCommandID(original)),
         }
     }
 }
 
 impl CommandID {
+    /// Converts a `u8` to a `CommandID`, keeping identifiers not defined by the ANCS
+    /// specification as `CommandID::Unknown`. Assigned identifiers always map to their
+    /// named variant, so values built this way compare equal exactly when they
+    /// encode to the same byte.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::command::CommandID;
+    /// assert_eq!(CommandID::from_u8(0), CommandID::GetNotificationAttributes);
+    /// assert_eq!(CommandID::from_u8(200), CommandID::Unknown(200));
+    ///
+    /// let (_, id) = CommandID::parse_with(&[0], ParseOptions::lenient()).unwrap();
+    /// assert_eq!(id, CommandID::GetNotificationAttributes);
+    /// ```
+    pub fn from_u8(id: u8) -> CommandID {
+        CommandID::try_from(id).unwrap_or(CommandID::Unknown(id))
+    }
+
     /// Attempts to parse a `CommandID` from a `&[u8]`
     /// 
     /// # Examples
@@ -61,15 +86,18 @@ impl CommandID {
     /// 
     /// assert_eq!(CommandID::GetNotificationAttributes, command_id);
     /// ```
-    pub fn parse(i: &[u8]) -> IResult<&[u8], CommandID> {
-        let (i, command_id) = le_u8(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], CommandID, Error> {
+        CommandID::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `CommandID` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], CommandID, Error> {
+        let (i, command_id) = parse::le_u8(i, options)?;
 
         match CommandID::try_from(command_id) {
-            Ok(command_id) => { Ok((i, command_id)) },
-            Err(_) => { Err(nom::Err::Failure(ParseError::from_error_kind(i, nom::error::ErrorKind::Fail))) },
+            Ok(command_id) => Ok((i, command_id)),
+            Err(_) if options.allow_unknown_ids => Ok((i, CommandID::from_u8(command_id))),
+            Err(error) => Err(nom::Err::Failure(error)),
         }
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct CommandIDError;
\ No newline at end of file
@@ -1,14 +1,19 @@
+use crate::{parse, Error, ParseOptions};
+
 use bitflags::bitflags;
-use nom::{
-    number::complete::{le_u8},
-    IResult, error::ParseError,
-};
+use nom::IResult;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EventID {
-    NotificationAdded = 0,
-    NotificationModified = 1,
-    NotificationRemoved = 2,
+    NotificationAdded,
+    NotificationModified,
+    NotificationRemoved,
+    /// An identifier not defined by the ANCS specification, kept as received.
+    /// It never holds the byte of a named variant: parsers and `from_u8` only
+    /// produce it for unassigned identifiers, and building one by hand for an
+    /// assigned identifier is a logic error.
+    Unknown(u8),
 }
 
 impl From<EventID> for u8 {
@@ -26,12 +31,13 @@ impl From<EventID> for u8 {
             EventID::NotificationAdded => 0,
             EventID::NotificationModified => 1,
             EventID::NotificationRemoved => 2,
+            EventID::Unknown(id) => id,
         }
     }
 }
 
 impl TryFrom<u8> for EventID {
-    type Error = EventIDError;
+    type Error = Error;
     /// Attempts to convert a `u8` to a `EventID`
     /// 
     /// # Examples
@@ -46,12 +52,31 @@ impl TryFrom<u8> for EventID {
             0 => Ok(EventID::NotificationAdded),
             1 => Ok(EventID::NotificationModified),
             2 => Ok(EventID::NotificationRemoved),
-            _ => Err(EventIDError),
+            _ => Err(Error::InvalidEventID(original)),
         }
     }
 }
 
 impl EventID {
+    /// Converts a `u8` to a `EventID`, keeping identifiers not defined by the ANCS
+    /// specification as `EventID::Unknown`. Assigned identifiers always map to their
+    /// named variant, so values built this way compare equal exactly when they
+    /// encode to the same byte.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::event::EventID;
+    /// assert_eq!(EventID::from_u8(0), EventID::NotificationAdded);
+    /// assert_eq!(EventID::from_u8(200), EventID::Unknown(200));
+    ///
+    /// let (_, id) = EventID::parse_with(&[0], ParseOptions::lenient()).unwrap();
+    /// assert_eq!(id, EventID::NotificationAdded);
+    /// ```
+    pub fn from_u8(id: u8) -> EventID {
+        EventID::try_from(id).unwrap_or(EventID::Unknown(id))
+    }
+
     /// Attempts to parse a `EventID` from a `&[u8]`
     /// 
     /// # Examples
@@ -63,21 +88,25 @@ impl EventID {
     /// assert_eq!(EventID::NotificationAdded, event_id);
     /// ```
     /// 
-    pub fn parse(i: &[u8]) -> IResult<&[u8], EventID> {
-        let (i, event_id) = le_u8(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], EventID, Error> {
+        EventID::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `EventID` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], EventID, Error> {
+        let (i, event_id) = parse::le_u8(i, options)?;
 
         match EventID::try_from(event_id) {
-            Ok(event_id) => { Ok((i, event_id)) },
-            Err(_) => Err(nom::Err::Failure(ParseError::from_error_kind(i, nom::error::ErrorKind::Fail))),
+            Ok(event_id) => Ok((i, event_id)),
+            Err(_) if options.allow_unknown_ids => Ok((i, EventID::from_u8(event_id))),
+            Err(error) => Err(nom::Err::Failure(error)),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct EventIDError;
-
 bitflags! {
-    #[derive(Debug, PartialEq, Clone, Copy)]
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct EventFlag: u8 {
         const Silent = 0b00000001;
         const Important = 0b00000010;
@@ -103,24 +132,31 @@ impl From<EventFlag> for u8 {
 }
 
 impl TryFrom<u8> for EventFlag {
-    type Error = EventFlagError;
+    type Error = Error;
 
-    /// Attempts to convert a `u8` to a `EventFlag`
+    /// Attempts to convert a `u8` to a `EventFlag`, rejecting bits reserved by
+    /// the ANCS specification. Use `EventFlag::from_bits_retain` to keep them.
     /// 
     /// # Examples
     /// ```
+    /// # use ancs::Error;
     /// # use ancs::attributes::event::EventFlag;
     /// let event_flag: EventFlag = EventFlag::try_from(0b00000001).unwrap();
     /// 
     /// assert_eq!(EventFlag::Silent, event_flag);
+    /// assert_eq!(EventFlag::try_from(0b10000001), Err(Error::InvalidEventFlag(0b10000001)));
     /// ```
     fn try_from(original: u8) -> Result<Self, Self::Error> {
-        EventFlag::from_bits(original).ok_or(EventFlagError)
+        EventFlag::from_bits(original).ok_or(Error::InvalidEventFlag(original))
     }
 }
 
 impl EventFlag {
     /// Attempts to parse a `EventFlag` from a `&[u8]`
+    ///
+    /// Bits reserved by the ANCS specification are kept as received, so they
+    /// can be inspected with `EventFlag::reserved_bits` and are encoded again
+    /// unchanged.
     /// 
     /// # Examples
     /// ```
@@ -130,16 +166,54 @@ impl EventFlag {
     /// 
     /// assert_eq!(EventFlag::Silent, event_flag);
     /// ```
+    ///
+    /// ```
+    /// # use ancs::attributes::event::EventFlag;
+    /// let data: [u8; 1] = [0b10000010];
+    /// let (_, event_flag) = EventFlag::parse(&data).unwrap();
+    ///
+    /// assert_eq!(EventFlag::Important, event_flag.known());
+    /// assert_eq!(0b10000000, event_flag.reserved_bits());
+    /// assert_eq!(0b10000010_u8, event_flag.into());
+    /// ```
     /// 
-    pub fn parse(i: &[u8]) -> IResult<&[u8], EventFlag> {
-        let (i, event_flag) = le_u8(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], EventFlag, Error> {
+        EventFlag::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `EventFlag` from a `&[u8]` using the given `ParseOptions`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::{Error, ParseOptions};
+    /// # use ancs::attributes::event::EventFlag;
+    /// let data: [u8; 1] = [0b10000010];
+    ///
+    /// assert_eq!(
+    ///     EventFlag::parse_with(&data, ParseOptions::strict()),
+    ///     Err(nom::Err::Failure(Error::InvalidEventFlag(0b10000010)))
+    /// );
+    /// ```
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], EventFlag, Error> {
+        let (i, event_flag) = parse::le_u8(i, options)?;
+
+        if !options.reject_reserved_flags {
+            return Ok((i, EventFlag::from_bits_retain(event_flag)));
+        }
 
         match EventFlag::try_from(event_flag) {
-            Ok(event_flag) => { Ok((i, event_flag)) },
-            Err(_) => Err(nom::Err::Failure(ParseError::from_error_kind(i, nom::error::ErrorKind::Fail))),
+            Ok(event_flag) => Ok((i, event_flag)),
+            Err(error) => Err(nom::Err::Failure(error)),
         }
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct EventFlagError;
+    /// Returns only the flags defined by the ANCS specification.
+    pub fn known(&self) -> EventFlag {
+        self.intersection(EventFlag::all())
+    }
+
+    /// Returns the bits reserved by the ANCS specification that are set.
+    pub fn reserved_bits(&self) -> u8 {
+        self.bits() & !EventFlag::all().bits()
+    }
+}
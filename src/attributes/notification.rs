@@ -1,20 +1,25 @@
-use nom::{
-    number::complete::{le_u8},
-    IResult, error::ParseError,
-};
+use crate::{parse, Error, ParseOptions};
+
+use nom::IResult;
 
 /// Provides a set of identifiers for types of attributes that a consumer may require.
 /// This list of `NotificationAttributeID`s follows the ANCS Specification for valid NotificationAttributeIDs
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NotificationAttributeID {
-    AppIdentifier = 0,
-    Title = 1,
-    Subtitle = 2,
-    Message = 3,
-    MessageSize = 4,
-    Date = 5,
-    PositiveActionLabel = 6,
-    NegativeActionLabel = 7,
+    AppIdentifier,
+    Title,
+    Subtitle,
+    Message,
+    MessageSize,
+    Date,
+    PositiveActionLabel,
+    NegativeActionLabel,
+    /// An identifier not defined by the ANCS specification, kept as received.
+    /// It never holds the byte of a named variant: parsers and `from_u8` only
+    /// produce it for unassigned identifiers, and building one by hand for an
+    /// assigned identifier is a logic error.
+    Unknown(u8),
 }
 
 impl From<NotificationAttributeID> for u8 {
@@ -37,12 +42,13 @@ impl From<NotificationAttributeID> for u8 {
             NotificationAttributeID::Date => 5,
             NotificationAttributeID::PositiveActionLabel => 6,
             NotificationAttributeID::NegativeActionLabel => 7,
+            NotificationAttributeID::Unknown(id) => id,
         }
     }
 }
 
 impl TryFrom<u8> for NotificationAttributeID {
-    type Error = NotificationAttributeIDError;
+    type Error = Error;
 
     /// Attempts to convert a `u8` to a `NotificationAttributeID`
     /// 
@@ -64,12 +70,31 @@ impl TryFrom<u8> for NotificationAttributeID {
             5 => Ok(NotificationAttributeID::Date),
             6 => Ok(NotificationAttributeID::PositiveActionLabel),
             7 => Ok(NotificationAttributeID::NegativeActionLabel),
-            _ => Err(NotificationAttributeIDError),
+            _ => Err(Error::InvalidNotificationAttributeID(original)),
         }
     }
 }
 
 impl NotificationAttributeID {
+    /// Converts a `u8` to a `NotificationAttributeID`, keeping identifiers not defined by the ANCS
+    /// specification as `NotificationAttributeID::Unknown`. Assigned identifiers always map to their
+    /// named variant, so values built this way compare equal exactly when they
+    /// encode to the same byte.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// assert_eq!(NotificationAttributeID::from_u8(0), NotificationAttributeID::AppIdentifier);
+    /// assert_eq!(NotificationAttributeID::from_u8(200), NotificationAttributeID::Unknown(200));
+    ///
+    /// let (_, id) = NotificationAttributeID::parse_with(&[0], ParseOptions::lenient()).unwrap();
+    /// assert_eq!(id, NotificationAttributeID::AppIdentifier);
+    /// ```
+    pub fn from_u8(id: u8) -> NotificationAttributeID {
+        NotificationAttributeID::try_from(id).unwrap_or(NotificationAttributeID::Unknown(id))
+    }
+
     /// Attempts to parse a `NotificationAttributeID` from a `&[u8]`
     /// 
     /// # Examples
@@ -81,12 +106,18 @@ impl NotificationAttributeID {
     /// assert_eq!(NotificationAttributeID::AppIdentifier, notification_attribute_id);
     /// ```
     /// 
-    pub fn parse(i: &[u8]) -> IResult<&[u8], NotificationAttributeID> {
-        let (i, notification_attribute_id) = le_u8(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], NotificationAttributeID, Error> {
+        NotificationAttributeID::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `NotificationAttributeID` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], NotificationAttributeID, Error> {
+        let (i, notification_attribute_id) = parse::le_u8(i, options)?;
 
         match NotificationAttributeID::try_from(notification_attribute_id) {
-            Ok(notification_attribute_id) => { Ok((i, notification_attribute_id)) },
-            Err(_) => Err(nom::Err::Failure(ParseError::from_error_kind(i, nom::error::ErrorKind::Fail))),
+            Ok(notification_attribute_id) => Ok((i, notification_attribute_id)),
+            Err(_) if options.allow_unknown_ids => Ok((i, NotificationAttributeID::from_u8(notification_attribute_id))),
+            Err(error) => Err(nom::Err::Failure(error)),
         }
     }
 
@@ -104,14 +135,11 @@ impl NotificationAttributeID {
     /// ```
     /// 
     pub fn is_sized(id: NotificationAttributeID) -> bool {
-        match id {
-            NotificationAttributeID::Title => true,
-            NotificationAttributeID::Subtitle => true,
-            NotificationAttributeID::Message => true,
-            _ => false
-        }
+        matches!(
+            id,
+            NotificationAttributeID::Title
+                | NotificationAttributeID::Subtitle
+                | NotificationAttributeID::Message
+        )
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct NotificationAttributeIDError;
\ No newline at end of file
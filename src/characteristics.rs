@@ -9,3 +9,51 @@
 pub mod control_point;
 pub mod data_source;
 pub mod notification_source;
+
+use crate::attributes::command::CommandID;
+use crate::{parse, Error, ParseOptions};
+
+use nom::IResult;
+
+/// Encodes an app identifier as the NULL terminated UTF-8 string ANCS expects.
+///
+/// Rust strings are not null terminated by default however it is possible that
+/// the user knows to insert a null terminated string of some kind, so a single
+/// trailing NULL is accepted. Empty identifiers and identifiers with an embedded
+/// NULL cannot be represented on the wire and are rejected.
+pub(crate) fn encode_app_identifier(app_identifier: String) -> Result<Vec<u8>, Error> {
+    let mut bytes: Vec<u8> = app_identifier.into_bytes();
+
+    if bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+
+    if bytes.is_empty() || bytes.contains(&0) {
+        return Err(Error::InvalidAppIdentifier);
+    }
+
+    bytes.push(0);
+
+    Ok(bytes)
+}
+
+/// Parses a NULL terminated UTF-8 app identifier.
+pub(crate) fn parse_app_identifier(i: &[u8], options: ParseOptions) -> IResult<&[u8], String, Error> {
+    let (i, app_identifier) = parse::take_till_nul(i, options)?;
+    let (i, _) = parse::le_u8(i, options)?;
+
+    match String::from_utf8(app_identifier.to_vec()) {
+        Ok(app_identifier) => Ok((i, app_identifier)),
+        Err(_) => Err(nom::Err::Failure(Error::InvalidUtf8)),
+    }
+}
+
+/// Parses a `CommandID`, failing unless it is the one the caller expects.
+pub(crate) fn expect_command_id(i: &[u8], expected: CommandID, options: ParseOptions) -> IResult<&[u8], CommandID, Error> {
+    let (i, command_id) = CommandID::parse_with(i, options)?;
+
+    match command_id == expected {
+        true => Ok((i, command_id)),
+        false => Err(nom::Err::Failure(Error::UnexpectedCommandID(command_id.into()))),
+    }
+}
@@ -2,29 +2,155 @@ use crate::attributes::action::ActionID;
 use crate::attributes::app::AppAttributeID;
 use crate::attributes::notification::NotificationAttributeID;
 use crate::attributes::command::*;
+use crate::characteristics::{encode_app_identifier, expect_command_id, parse_app_identifier};
+use crate::{parse, Error, ParseOptions};
 
 use nom::{
-    bytes::complete::{take_till},
-    combinator::{opt, fail},
-    number::complete::le_u16,
-    combinator::{verify},
+    combinator::cut,
     multi::{many0},
-    number::complete::{le_u8, le_u32},
-    branch::{alt},
-    sequence::{pair, terminated},
+    number::complete::le_u32,
     IResult,
 };
 use uuid::{uuid, Uuid};
 
 pub const CONTROL_POINT_UUID: Uuid = uuid!("69D1D8F3-45E1-49A8-9821-9BBDFDAAD9D9");
 
+/// A notification attribute to request, together with the maximum length the
+/// ANCS specification requires for `Title`, `Subtitle` and `Message`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RequestedAttribute {
+    AppIdentifier,
+    /// The title, truncated by the Notification Provider to the given maximum length.
+    Title(u16),
+    /// The subtitle, truncated by the Notification Provider to the given maximum length.
+    Subtitle(u16),
+    /// The message, truncated by the Notification Provider to the given maximum length.
+    Message(u16),
+    MessageSize,
+    Date,
+    PositiveActionLabel,
+    NegativeActionLabel,
+}
+
+impl From<RequestedAttribute> for Vec<u8> {
+    /// Converts a `RequestedAttribute` to a `Vec<u8>`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::RequestedAttribute;
+    /// let data: Vec<u8> = RequestedAttribute::Title(32).into();
+    ///
+    /// assert_eq!(data, vec![1, 32, 0]);
+    /// ```
+    fn from(original: RequestedAttribute) -> Vec<u8> {
+        let mut vec: Vec<u8> = vec![original.id().into()];
+
+        if let Some(max_length) = original.max_length() {
+            vec.extend(max_length.to_le_bytes());
+        }
+
+        vec
+    }
+}
+
+impl RequestedAttribute {
+    /// Attempts to create a `RequestedAttribute`, requiring a maximum length
+    /// exactly when `NotificationAttributeID::is_sized` is true.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// # use ancs::characteristics::control_point::RequestedAttribute;
+    /// assert_eq!(RequestedAttribute::new(NotificationAttributeID::Message, Some(64)), Ok(RequestedAttribute::Message(64)));
+    /// assert_eq!(
+    ///     RequestedAttribute::new(NotificationAttributeID::Title, None),
+    ///     Err(Error::MissingMaxLength(NotificationAttributeID::Title))
+    /// );
+    /// assert_eq!(
+    ///     RequestedAttribute::new(NotificationAttributeID::AppIdentifier, Some(64)),
+    ///     Err(Error::UnexpectedMaxLength(NotificationAttributeID::AppIdentifier))
+    /// );
+    /// ```
+    pub fn new(id: NotificationAttributeID, max_length: Option<u16>) -> Result<RequestedAttribute, Error> {
+        match (id, max_length) {
+            (NotificationAttributeID::Title, Some(max_length)) => Ok(RequestedAttribute::Title(max_length)),
+            (NotificationAttributeID::Subtitle, Some(max_length)) => Ok(RequestedAttribute::Subtitle(max_length)),
+            (NotificationAttributeID::Message, Some(max_length)) => Ok(RequestedAttribute::Message(max_length)),
+            (NotificationAttributeID::Title | NotificationAttributeID::Subtitle | NotificationAttributeID::Message, None) => {
+                Err(Error::MissingMaxLength(id))
+            },
+            (NotificationAttributeID::AppIdentifier, None) => Ok(RequestedAttribute::AppIdentifier),
+            (NotificationAttributeID::MessageSize, None) => Ok(RequestedAttribute::MessageSize),
+            (NotificationAttributeID::Date, None) => Ok(RequestedAttribute::Date),
+            (NotificationAttributeID::PositiveActionLabel, None) => Ok(RequestedAttribute::PositiveActionLabel),
+            (NotificationAttributeID::NegativeActionLabel, None) => Ok(RequestedAttribute::NegativeActionLabel),
+            (NotificationAttributeID::Unknown(id), _) => Err(Error::InvalidNotificationAttributeID(id)),
+            (id, Some(_)) => Err(Error::UnexpectedMaxLength(id)),
+        }
+    }
+
+    /// Returns the `NotificationAttributeID` being requested.
+    pub fn id(&self) -> NotificationAttributeID {
+        match self {
+            RequestedAttribute::AppIdentifier => NotificationAttributeID::AppIdentifier,
+            RequestedAttribute::Title(_) => NotificationAttributeID::Title,
+            RequestedAttribute::Subtitle(_) => NotificationAttributeID::Subtitle,
+            RequestedAttribute::Message(_) => NotificationAttributeID::Message,
+            RequestedAttribute::MessageSize => NotificationAttributeID::MessageSize,
+            RequestedAttribute::Date => NotificationAttributeID::Date,
+            RequestedAttribute::PositiveActionLabel => NotificationAttributeID::PositiveActionLabel,
+            RequestedAttribute::NegativeActionLabel => NotificationAttributeID::NegativeActionLabel,
+        }
+    }
+
+    /// Returns the maximum length requested for sized attributes.
+    pub fn max_length(&self) -> Option<u16> {
+        match self {
+            RequestedAttribute::Title(max_length)
+            | RequestedAttribute::Subtitle(max_length)
+            | RequestedAttribute::Message(max_length) => Some(*max_length),
+            _ => None,
+        }
+    }
+
+    /// Attempts to parse a `RequestedAttribute` from a `&[u8]`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::RequestedAttribute;
+    /// let data: Vec<u8> = vec![3, 255, 0, 0];
+    /// let (data, attribute) = RequestedAttribute::parse(&data).unwrap();
+    ///
+    /// assert_eq!(attribute, RequestedAttribute::Message(255));
+    /// assert_eq!(data, [0]);
+    /// ```
+    pub fn parse(i: &[u8]) -> IResult<&[u8], RequestedAttribute, Error> {
+        RequestedAttribute::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `RequestedAttribute` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], RequestedAttribute, Error> {
+        let (i, id) = NotificationAttributeID::parse_with(i, options)?;
+
+        // Once the identifier is read a missing length is an error rather
+        // than the end of the attribute list.
+        let (i, max_length) = match NotificationAttributeID::is_sized(id) {
+            true => cut(|i| parse::le_u16(i, options))(i).map(|(i, max_length)| (i, Some(max_length)))?,
+            false => (i, None),
+        };
+
+        match RequestedAttribute::new(id, max_length) {
+            Ok(attribute) => Ok((i, attribute)),
+            Err(error) => Err(nom::Err::Failure(error)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct GetNotificationAttributesRequest {
-    pub command_id: CommandID,
     pub notification_uid: u32,
-    // Rust doesn't have a clean way to express variadic tuples, and Apple has decided that some attributes need a max_length
-    // assigned. To ensure that users can serialize requests without losing data we're left with this terrible solution of 
-    // having users optionally provide a length or not I can't come up with a good name for a structure here so we're left with this.
-    pub attribute_ids: Vec<(NotificationAttributeID, Option<u16>)>,
+    pub attribute_ids: Vec<RequestedAttribute>,
 }
 
 impl From<GetNotificationAttributesRequest> for Vec<u8> {
@@ -32,13 +158,10 @@ impl From<GetNotificationAttributesRequest> for Vec<u8> {
     /// 
     /// # Examples
     /// ```
-    /// # use ancs::attributes::command::CommandID;
-    /// # use ancs::attributes::notification::NotificationAttributeID;
-    /// # use ancs::characteristics::control_point::GetNotificationAttributesRequest;
+    /// # use ancs::characteristics::control_point::{GetNotificationAttributesRequest, RequestedAttribute};
     /// let notification: GetNotificationAttributesRequest = GetNotificationAttributesRequest {
-    ///    command_id: CommandID::GetNotificationAttributes,
     ///    notification_uid: 4294967295_u32,
-    ///    attribute_ids: vec![(NotificationAttributeID::AppIdentifier, None), (NotificationAttributeID::Title, Some(u16::MAX))],
+    ///    attribute_ids: vec![RequestedAttribute::AppIdentifier, RequestedAttribute::Title(u16::MAX)],
     /// };
     /// 
     /// let data: Vec<u8> = notification.into();
@@ -47,24 +170,13 @@ impl From<GetNotificationAttributesRequest> for Vec<u8> {
     /// assert_eq!(data, expected_data)
     /// ```
     fn from(original: GetNotificationAttributesRequest) -> Vec<u8> {
-        let id = original.command_id as u8;
+        let id: u8 = CommandID::GetNotificationAttributes.into();
         let notification_uid: [u8; 4] = original.notification_uid.to_le_bytes();
-        let mut attribute_ids: Vec<u8> = Vec::new();
-
-        original.attribute_ids.into_iter().for_each(|id| {
-            match id.1 {
-                Some(length) => {
-                    let byte: u8 = id.0.into();
-                    let length_bytes: [u8; 2] = length.to_le_bytes();
-                    attribute_ids.push(byte);
-                    attribute_ids.extend(length_bytes);
-                },
-                None => {
-                    let byte: u8 = id.0.into();
-                    attribute_ids.push(byte);
-                }
-            };
-        });
+        let mut attribute_ids: Vec<u8> = original
+            .attribute_ids
+            .into_iter()
+            .flat_map(Vec::<u8>::from)
+            .collect();
 
         let mut v: Vec<u8> = Vec::new();
 
@@ -72,7 +184,7 @@ impl From<GetNotificationAttributesRequest> for Vec<u8> {
         v.extend(notification_uid);
         v.append(&mut attribute_ids);
 
-        return v;
+        v
     }
 }
 
@@ -81,9 +193,7 @@ impl GetNotificationAttributesRequest {
     /// 
     /// # Examples
     /// ```
-    /// # use ancs::attributes::command::CommandID;
-    /// # use ancs::attributes::notification::NotificationAttributeID;
-    /// # use ancs::characteristics::control_point::GetNotificationAttributesRequest;
+    /// # use ancs::characteristics::control_point::{GetNotificationAttributesRequest, RequestedAttribute};
     /// let data: Vec<u8> = vec![
     ///     0, 
     ///     255,
@@ -97,88 +207,96 @@ impl GetNotificationAttributesRequest {
     /// ];
     /// let (data, notification) = GetNotificationAttributesRequest::parse(&data).unwrap();
     ///
-    /// assert_eq!(notification.command_id, CommandID::GetNotificationAttributes);
     /// assert_eq!(notification.notification_uid, 4294967295_u32);
-    /// assert_eq!(notification.attribute_ids, vec![(NotificationAttributeID::AppIdentifier, None), (NotificationAttributeID::Title, Some(u16::MAX))]);
+    /// assert_eq!(notification.attribute_ids, vec![RequestedAttribute::AppIdentifier, RequestedAttribute::Title(u16::MAX)]);
+    /// ```
+    ///
+    /// A sized attribute without its maximum length is rejected:
     /// ```
-    pub fn parse(i: &[u8]) -> IResult<&[u8], GetNotificationAttributesRequest> {
-        let (i, command_id) = CommandID::parse(i)?;
+    /// # use ancs::Error;
+    /// # use ancs::characteristics::control_point::GetNotificationAttributesRequest;
+    /// let data: Vec<u8> = vec![0, 1, 0, 0, 0, 1];
+    ///
+    /// assert_eq!(GetNotificationAttributesRequest::parse(&data).err(), Some(nom::Err::Failure(Error::Truncated)));
+    /// ```
+    pub fn parse(i: &[u8]) -> IResult<&[u8], GetNotificationAttributesRequest, Error> {
+        GetNotificationAttributesRequest::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `GetNotificationAttributesRequest` from a `&[u8]` using the given `ParseOptions`
+    ///
+    /// Control Point writes always arrive whole, so `ParseOptions::streaming` is ignored.
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], GetNotificationAttributesRequest, Error> {
+        let options = ParseOptions { streaming: false, ..options };
+        let (i, _) = expect_command_id(i, CommandID::GetNotificationAttributes, options)?;
         let (i, notification_uid) = le_u32(i)?;
-        let (i, attribute_ids) = many0(
-            alt((
-                pair(
-                    verify(NotificationAttributeID::parse, |&id| NotificationAttributeID::is_sized(id)),
-                    opt(le_u16),
-                ),
-                pair(
-                    verify(NotificationAttributeID::parse, |&id| !NotificationAttributeID::is_sized(id)),
-                    opt(fail),
-                ),
-            ))
-        )(i)?; 
+        let (i, attribute_ids) = many0(|i| RequestedAttribute::parse_with(i, options))(i)?;
 
         Ok((
             i,
             GetNotificationAttributesRequest {
-                command_id: command_id,
-                notification_uid: notification_uid,
-                attribute_ids: attribute_ids,
+                notification_uid,
+                attribute_ids,
             },
         ))
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct GetAppAttributesRequest {
-    pub command_id: CommandID,
     pub app_identifier: String,
     pub attribute_ids: Vec<AppAttributeID>,
 }
 
-impl From<GetAppAttributesRequest> for Vec<u8> {
+impl TryFrom<GetAppAttributesRequest> for Vec<u8> {
+    type Error = Error;
+
     /// Converts a `GetAppAttributesRequest` to a `Vec<u8>`
     /// 
     /// # Examples
     /// ```
-    /// # use ancs::attributes::command::CommandID;
     /// # use ancs::attributes::app::AppAttributeID;
     /// # use ancs::characteristics::control_point::GetAppAttributesRequest;
     /// let notification: GetAppAttributesRequest = GetAppAttributesRequest {
-    ///     command_id: CommandID::GetNotificationAttributes,
     ///     app_identifier: "com.apple.test".to_string(),
     ///     attribute_ids: vec![AppAttributeID::DisplayName],
     /// };
     ///
-    /// let data: Vec<u8> = notification.into();
-    /// let expected_data: Vec<u8> = vec![0, 99, 111, 109, 46, 97, 112, 112, 108, 101, 46, 116, 101, 115, 116, 0, 0];
+    /// let data: Vec<u8> = notification.try_into().unwrap();
+    /// let expected_data: Vec<u8> = vec![1, 99, 111, 109, 46, 97, 112, 112, 108, 101, 46, 116, 101, 115, 116, 0, 0];
     ///
     /// assert_eq!(data, expected_data)
     /// ```
-    fn from(original: GetAppAttributesRequest) -> Vec<u8> {
+    ///
+    /// An empty app identifier cannot be encoded:
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// # use ancs::characteristics::control_point::GetAppAttributesRequest;
+    /// let request: GetAppAttributesRequest = GetAppAttributesRequest {
+    ///     app_identifier: String::new(),
+    ///     attribute_ids: vec![AppAttributeID::DisplayName],
+    /// };
+    ///
+    /// assert_eq!(Vec::<u8>::try_from(request), Err(Error::InvalidAppIdentifier));
+    /// ```
+    fn try_from(original: GetAppAttributesRequest) -> Result<Vec<u8>, Self::Error> {
         let mut vec: Vec<u8> = Vec::new();
 
         // Convert all attributes to bytes
-        let command_id: u8 = original.command_id.into();
-        let mut app_identifier: Vec<u8> = original.app_identifier.into_bytes();
+        let command_id: u8 = CommandID::GetAppAttributes.into();
+        let mut app_identifier: Vec<u8> = encode_app_identifier(original.app_identifier)?;
         let mut attribute_ids: Vec<u8> = original
             .attribute_ids
             .into_iter()
             .map(|id| id.into())
             .collect();
 
-        // Rust strings are not null terminated by default 
-        // however it is possible that the user knows to insert
-        // a null terminated string of some kind this helps us
-        // ensure that all strings submitted to ANCS are null
-        // terminated UTF-8 byte strings.
-        if app_identifier.last().unwrap() != &0_u8 {
-            app_identifier.push(0);
-        }
-
         vec.push(command_id);
         vec.append(&mut app_identifier);
         vec.append(&mut attribute_ids);
 
-        return vec;
+        Ok(vec)
     }
 }
 
@@ -187,36 +305,50 @@ impl GetAppAttributesRequest {
     /// 
     /// # Examples
     /// ```
-    /// # use ancs::attributes::command::CommandID;
     /// # use ancs::attributes::app::AppAttributeID;
     /// # use ancs::characteristics::control_point::GetAppAttributesRequest;
-    /// let data: Vec<u8> = vec![0, 84, 101, 115, 116, 0, 0];
+    /// let data: Vec<u8> = vec![1, 84, 101, 115, 116, 0, 0];
     /// let (data, notification) = GetAppAttributesRequest::parse(&data).unwrap();
     ///
-    /// assert_eq!(notification.command_id, CommandID::GetNotificationAttributes);
     /// assert_eq!(notification.app_identifier, "Test");
     /// assert_eq!(notification.attribute_ids, vec![AppAttributeID::DisplayName]);
     /// ```
-    pub fn parse(i: &[u8]) -> IResult<&[u8], GetAppAttributesRequest> {
-        let (i, command_id) = CommandID::parse(i)?;
-        let (i, app_identifier) = terminated(take_till(|b| b == 0), le_u8)(i)?;
+    ///
+    /// Bytes written for another command are rejected:
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::characteristics::control_point::GetAppAttributesRequest;
+    /// let data: Vec<u8> = vec![0, 84, 101, 115, 116, 0, 0];
+    ///
+    /// assert_eq!(GetAppAttributesRequest::parse(&data).err(), Some(nom::Err::Failure(Error::UnexpectedCommandID(0))));
+    /// ```
+    pub fn parse(i: &[u8]) -> IResult<&[u8], GetAppAttributesRequest, Error> {
+        GetAppAttributesRequest::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `GetAppAttributesRequest` from a `&[u8]` using the given `ParseOptions`
+    ///
+    /// Control Point writes always arrive whole, so `ParseOptions::streaming` is ignored.
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], GetAppAttributesRequest, Error> {
+        let options = ParseOptions { streaming: false, ..options };
+        let (i, _) = expect_command_id(i, CommandID::GetAppAttributes, options)?;
+        let (i, app_identifier) = parse_app_identifier(i, options)?;
         let (i, attribute_ids) = many0(
-            AppAttributeID::parse
+            |i| AppAttributeID::parse_with(i, options)
         )(i)?; 
 
         Ok((
             i,
             GetAppAttributesRequest {
-                command_id: command_id,
-                app_identifier: String::from_utf8(app_identifier.to_vec()).unwrap(),
-                attribute_ids: attribute_ids,
+                app_identifier,
+                attribute_ids,
             },
         ))
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct PerformNotificationActionRequest {
-    pub command_id: CommandID,
     pub notification_uid: u32,
     pub action_id: ActionID,
 }
@@ -226,11 +358,9 @@ impl From<PerformNotificationActionRequest> for Vec<u8> {
     ///
     /// # Examples
     /// ```
-    /// # use ancs::attributes::command::CommandID;
     /// # use ancs::attributes::action::ActionID;
     /// # use ancs::characteristics::control_point::PerformNotificationActionRequest;
     /// let notification: PerformNotificationActionRequest = PerformNotificationActionRequest {
-    ///     command_id: CommandID::PerformNotificationAction,
     ///     notification_uid: 4294967295_u32,
     ///     action_id: ActionID::Positive,
     /// };
@@ -244,7 +374,7 @@ impl From<PerformNotificationActionRequest> for Vec<u8> {
         let mut vec: Vec<u8> = Vec::new();
 
         // Convert all attributes to bytes
-        let command_id: u8 = original.command_id.into();
+        let command_id: u8 = CommandID::PerformNotificationAction.into();
         let notification_uid: [u8; 4] = original.notification_uid.to_le_bytes();
         let action_id: u8 = original.action_id.into();
 
@@ -252,7 +382,7 @@ impl From<PerformNotificationActionRequest> for Vec<u8> {
         vec.extend(notification_uid);
         vec.push(action_id);
 
-        return vec;
+        vec
     }
 }
 
@@ -261,7 +391,6 @@ impl PerformNotificationActionRequest {
     ///
     /// # Examples
     /// ```
-    /// # use ancs::attributes::command::CommandID;
     /// # use ancs::attributes::action::ActionID;
     /// # use ancs::characteristics::control_point::PerformNotificationActionRequest;
     /// let data: Vec<u8> = vec![
@@ -274,22 +403,319 @@ impl PerformNotificationActionRequest {
     /// ];
     /// let (data, notification) = PerformNotificationActionRequest::parse(&data).unwrap();
     ///
-    /// assert_eq!(notification.command_id, CommandID::PerformNotificationAction);
     /// assert_eq!(notification.notification_uid, 4294967295_u32);
     /// assert_eq!(notification.action_id, ActionID::Positive);
     /// ```
-    pub fn parse(i: &[u8]) -> IResult<&[u8], PerformNotificationActionRequest> {
-        let (i, command_id) = CommandID::parse(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], PerformNotificationActionRequest, Error> {
+        PerformNotificationActionRequest::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `PerformNotificationActionRequest` from a `&[u8]` using the given `ParseOptions`
+    ///
+    /// Control Point writes always arrive whole, so `ParseOptions::streaming` is ignored.
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], PerformNotificationActionRequest, Error> {
+        let options = ParseOptions { streaming: false, ..options };
+        let (i, _) = expect_command_id(i, CommandID::PerformNotificationAction, options)?;
         let (i, notification_uid) = le_u32(i)?;
-        let (i, action_id) = ActionID::parse(i)?;
+        let (i, action_id) = ActionID::parse_with(i, options)?;
 
         Ok((
             i,
             PerformNotificationActionRequest {
-                command_id: command_id,
-                notification_uid: notification_uid,
-                action_id: action_id,
+                notification_uid,
+                action_id,
             },
         ))
     }
 }
+
+/// Any request that can be written to the Control Point. The command byte is
+/// derived from the variant, so it can never disagree with the request.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ControlPointRequest {
+    GetNotificationAttributes(GetNotificationAttributesRequest),
+    GetAppAttributes(GetAppAttributesRequest),
+    PerformNotificationAction(PerformNotificationActionRequest),
+}
+
+impl From<GetNotificationAttributesRequest> for ControlPointRequest {
+    fn from(original: GetNotificationAttributesRequest) -> ControlPointRequest {
+        ControlPointRequest::GetNotificationAttributes(original)
+    }
+}
+
+impl From<GetAppAttributesRequest> for ControlPointRequest {
+    fn from(original: GetAppAttributesRequest) -> ControlPointRequest {
+        ControlPointRequest::GetAppAttributes(original)
+    }
+}
+
+impl From<PerformNotificationActionRequest> for ControlPointRequest {
+    fn from(original: PerformNotificationActionRequest) -> ControlPointRequest {
+        ControlPointRequest::PerformNotificationAction(original)
+    }
+}
+
+impl TryFrom<ControlPointRequest> for Vec<u8> {
+    type Error = Error;
+
+    /// Converts a `ControlPointRequest` to a `Vec<u8>`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// # use ancs::characteristics::control_point::{ControlPointRequest, GetAppAttributesRequest};
+    /// let request: ControlPointRequest = GetAppAttributesRequest {
+    ///     app_identifier: "Test".to_string(),
+    ///     attribute_ids: vec![AppAttributeID::DisplayName],
+    /// }.into();
+    ///
+    /// let data: Vec<u8> = request.try_into().unwrap();
+    ///
+    /// assert_eq!(data, vec![1, 84, 101, 115, 116, 0, 0]);
+    /// ```
+    fn try_from(original: ControlPointRequest) -> Result<Vec<u8>, Self::Error> {
+        match original {
+            ControlPointRequest::GetNotificationAttributes(request) => Ok(request.into()),
+            ControlPointRequest::GetAppAttributes(request) => request.try_into(),
+            ControlPointRequest::PerformNotificationAction(request) => Ok(request.into()),
+        }
+    }
+}
+
+impl ControlPointRequest {
+    /// Returns the `CommandID` this request is written with.
+    pub fn command_id(&self) -> CommandID {
+        match self {
+            ControlPointRequest::GetNotificationAttributes(_) => CommandID::GetNotificationAttributes,
+            ControlPointRequest::GetAppAttributes(_) => CommandID::GetAppAttributes,
+            ControlPointRequest::PerformNotificationAction(_) => CommandID::PerformNotificationAction,
+        }
+    }
+
+    /// Attempts to parse a `ControlPointRequest` from a `&[u8]`, dispatching on the leading `CommandID`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::characteristics::control_point::{ControlPointRequest, PerformNotificationActionRequest};
+    /// let data: Vec<u8> = vec![2, 255, 255, 255, 255, 1];
+    /// let (_, request) = ControlPointRequest::parse(&data).unwrap();
+    ///
+    /// assert_eq!(request, ControlPointRequest::PerformNotificationAction(PerformNotificationActionRequest {
+    ///     notification_uid: 4294967295_u32,
+    ///     action_id: ActionID::Negative,
+    /// }));
+    ///
+    /// let data: Vec<u8> = vec![7, 0];
+    /// assert_eq!(ControlPointRequest::parse(&data).err(), Some(nom::Err::Failure(Error::InvalidCommandID(7))));
+    /// ```
+    pub fn parse(i: &[u8]) -> IResult<&[u8], ControlPointRequest, Error> {
+        ControlPointRequest::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `ControlPointRequest` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], ControlPointRequest, Error> {
+        let (_, command_id) = CommandID::parse_with(i, options)?;
+
+        match command_id {
+            CommandID::GetNotificationAttributes => GetNotificationAttributesRequest::parse_with(i, options)
+                .map(|(i, request)| (i, ControlPointRequest::GetNotificationAttributes(request))),
+            CommandID::GetAppAttributes => GetAppAttributesRequest::parse_with(i, options)
+                .map(|(i, request)| (i, ControlPointRequest::GetAppAttributes(request))),
+            CommandID::PerformNotificationAction => PerformNotificationActionRequest::parse_with(i, options)
+                .map(|(i, request)| (i, ControlPointRequest::PerformNotificationAction(request))),
+            CommandID::Unknown(id) => Err(nom::Err::Failure(Error::InvalidCommandID(id))),
+        }
+    }
+}
+
+/// The ATT error codes a Notification Provider returns when a write to the
+/// Control Point fails, as defined by the ANCS specification.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ControlPointError {
+    /// The commandID was not recognized by the NP.
+    UnknownCommand,
+    /// The command was improperly formatted.
+    InvalidCommand,
+    /// One of the parameters (for example, the NotificationUID) does not refer to an existing object on the NP.
+    InvalidParameter,
+    /// The action was not performed.
+    ActionFailed,
+}
+
+impl From<ControlPointError> for u8 {
+    /// Converts a `ControlPointError` to its ATT error code
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::ControlPointError;
+    /// let data: u8 = ControlPointError::UnknownCommand.into();
+    ///
+    /// assert_eq!(0xA0, data);
+    /// ```
+    fn from(original: ControlPointError) -> u8 {
+        match original {
+            ControlPointError::UnknownCommand => 0xA0,
+            ControlPointError::InvalidCommand => 0xA1,
+            ControlPointError::InvalidParameter => 0xA2,
+            ControlPointError::ActionFailed => 0xA3,
+        }
+    }
+}
+
+impl TryFrom<u8> for ControlPointError {
+    type Error = Error;
+
+    /// Attempts to convert an ATT error code to a `ControlPointError`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::characteristics::control_point::ControlPointError;
+    /// let error: ControlPointError = ControlPointError::try_from(0xA2).unwrap();
+    ///
+    /// assert_eq!(ControlPointError::InvalidParameter, error);
+    /// assert_eq!(ControlPointError::try_from(0x0E), Err(Error::InvalidControlPointError(0x0E)));
+    /// ```
+    fn try_from(original: u8) -> Result<Self, Self::Error> {
+        match original {
+            0xA0 => Ok(ControlPointError::UnknownCommand),
+            0xA1 => Ok(ControlPointError::InvalidCommand),
+            0xA2 => Ok(ControlPointError::InvalidParameter),
+            0xA3 => Ok(ControlPointError::ActionFailed),
+            _ => Err(Error::InvalidControlPointError(original)),
+        }
+    }
+}
+
+impl ControlPointError {
+    /// Returns the description the ANCS specification gives for the error code.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::ControlPointError;
+    /// assert_eq!(ControlPointError::ActionFailed.description(), "The action was not performed.");
+    /// ```
+    pub fn description(&self) -> &'static str {
+        match self {
+            ControlPointError::UnknownCommand => "The commandID was not recognized by the NP.",
+            ControlPointError::InvalidCommand => "The command was improperly formatted.",
+            ControlPointError::InvalidParameter => {
+                "One of the parameters (for example, the NotificationUID) does not refer to an existing object on the NP."
+            },
+            ControlPointError::ActionFailed => "The action was not performed.",
+        }
+    }
+
+    /// Determines if the error points at a malformed or unsupported request
+    /// rather than at the state of the Notification Provider.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::ControlPointError;
+    /// assert_eq!(ControlPointError::InvalidCommand.is_protocol_error(), true);
+    /// assert_eq!(ControlPointError::InvalidParameter.is_protocol_error(), false);
+    /// ```
+    pub fn is_protocol_error(&self) -> bool {
+        matches!(self, ControlPointError::UnknownCommand | ControlPointError::InvalidCommand)
+    }
+}
+
+impl std::fmt::Display for ControlPointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#04X}: {}", u8::from(*self), self.description())
+    }
+}
+
+impl std::error::Error for ControlPointError {}
+
+/// Why a write to the Control Point failed: either the Notification Provider
+/// answered with a `ControlPointError`, or the transport itself failed with `E`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum WriteError<E> {
+    Rejected(ControlPointError),
+    Transport(E),
+}
+
+impl<E> From<ControlPointError> for WriteError<E> {
+    fn from(original: ControlPointError) -> WriteError<E> {
+        WriteError::Rejected(original)
+    }
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for WriteError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteError::Rejected(error) => write!(f, "write rejected: {}", error),
+            WriteError::Transport(error) => write!(f, "transport error: {}", error),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for WriteError<E> {}
+
+/// The result of a Control Point write, attached to the request that caused it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ControlPointOutcome<R> {
+    pub request: R,
+    pub result: Result<(), ControlPointError>,
+}
+
+impl<R> ControlPointOutcome<R> {
+    /// Creates a `ControlPointOutcome` for a write the Notification Provider accepted.
+    pub fn accepted(request: R) -> ControlPointOutcome<R> {
+        ControlPointOutcome { request, result: Ok(()) }
+    }
+
+    /// Creates a `ControlPointOutcome` for a write the Notification Provider rejected.
+    pub fn rejected(request: R, error: ControlPointError) -> ControlPointOutcome<R> {
+        ControlPointOutcome { request, result: Err(error) }
+    }
+
+    /// Creates a `ControlPointOutcome` from the ATT status of a write, where `0`
+    /// indicates success.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::characteristics::control_point::{ControlPointError, ControlPointOutcome, PerformNotificationActionRequest};
+    /// let request = PerformNotificationActionRequest {
+    ///     notification_uid: 42,
+    ///     action_id: ActionID::Negative,
+    /// };
+    /// let outcome = ControlPointOutcome::from_status(request, 0xA2).unwrap();
+    ///
+    /// assert_eq!(outcome.error(), Some(ControlPointError::InvalidParameter));
+    /// assert_eq!(outcome.is_stale(), true);
+    /// assert_eq!(outcome.request.notification_uid, 42);
+    /// ```
+    pub fn from_status(request: R, status: u8) -> Result<ControlPointOutcome<R>, Error> {
+        match status {
+            0 => Ok(ControlPointOutcome::accepted(request)),
+            status => Ok(ControlPointOutcome::rejected(request, ControlPointError::try_from(status)?)),
+        }
+    }
+
+    /// Determines if the Notification Provider accepted the request.
+    pub fn is_accepted(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Returns the error the Notification Provider rejected the request with, if any.
+    pub fn error(&self) -> Option<ControlPointError> {
+        self.result.err()
+    }
+
+    /// Determines if the request was rejected because it refers to something that
+    /// no longer exists on the Notification Provider, such as a removed notification.
+    pub fn is_stale(&self) -> bool {
+        self.result == Err(ControlPointError::InvalidParameter)
+    }
+
+    /// Determines if the request was rejected because it was malformed or unsupported.
+    pub fn is_protocol_error(&self) -> bool {
+        self.error().map(|error| error.is_protocol_error()).unwrap_or(false)
+    }
+}
@@ -1,19 +1,21 @@
 use crate::attributes::AppAttribute;
 use crate::attributes::NotificationAttribute;
 use crate::attributes::command::*;
+use crate::characteristics::control_point::{ControlPointRequest, GetAppAttributesRequest, GetNotificationAttributesRequest};
+use crate::characteristics::{encode_app_identifier, expect_command_id, parse_app_identifier};
+use crate::{parse, Error, ParseOptions};
 
-use nom::combinator::all_consuming;
-use nom::{
-    bytes::complete::take_till,
-    multi::{many0},
-    number::complete::{le_u8, le_u32},
-    sequence::{terminated},
-    IResult,
-};
+use nom::IResult;
 use uuid::{uuid, Uuid};
 
 pub const DATA_SOURCE_UUID: Uuid = uuid!("22EAC6E9-24D6-4BB5-BE44-B36ACE7C7BFB");
 
+/// The ATT MTU every connection starts with, until a larger one is negotiated.
+pub const DEFAULT_ATT_MTU: u16 = 23;
+
+/// The bytes of every ATT notification taken by its opcode and attribute handle.
+pub const ATT_NOTIFICATION_HEADER: u16 = 3;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct GetNotificationAttributesResponse {
     pub command_id: CommandID,
@@ -21,7 +23,9 @@ pub struct GetNotificationAttributesResponse {
     pub attribute_list: Vec<NotificationAttribute>,
 }
 
-impl From<GetNotificationAttributesResponse> for Vec<u8> {
+impl TryFrom<GetNotificationAttributesResponse> for Vec<u8> {
+    type Error = Error;
+
     /// Converts a `GetNotificationAttributesResponse` to a `Vec<u8>`
     /// 
     /// # Examples
@@ -42,30 +46,24 @@ impl From<GetNotificationAttributesResponse> for Vec<u8> {
     ///     ],
     /// };
     ///
-    /// let data: Vec<u8> = notification.into();
+    /// let data: Vec<u8> = notification.try_into().unwrap();
     /// let expected_data: Vec<u8> = vec![0, 255, 255, 255, 255, 0, 13, 0, 99, 111, 109, 46, 114, 117, 115, 116, 46, 116, 101, 115, 116];
     /// 
     /// assert_eq!(data, expected_data)
     /// ```
-    fn from(original: GetNotificationAttributesResponse) -> Vec<u8> {
+    fn try_from(original: GetNotificationAttributesResponse) -> Result<Vec<u8>, Self::Error> {
         let mut vec: Vec<u8> = Vec::new();
 
         // Convert all attributes to bytes
         let command_id: u8 = original.command_id.into();
         let notification_uid: [u8; 4] = original.notification_uid.to_le_bytes();
-        let mut attribute_ids: Vec<u8> = original
-            .attribute_list
-            .into_iter()
-            .map(|attribute| attribute.into())
-            .into_iter()
-            .flat_map(|att: Vec<u8>| att)
-            .collect();
+        let mut attribute_ids: Vec<u8> = encode_attributes(original.attribute_list)?;
 
         vec.push(command_id);
-        vec.append(&mut notification_uid.into());
+        vec.extend(notification_uid);
         vec.append(&mut attribute_ids);
 
-        return vec;
+        Ok(vec)
     }
 }
 
@@ -91,17 +89,46 @@ impl GetNotificationAttributesResponse {
     ///    }
     /// ]);
     /// ```
-    pub fn parse(i: &[u8]) -> IResult<&[u8], GetNotificationAttributesResponse> {
-        let (i, command_id) = CommandID::parse(i)?;
-        let (i, notification_uid) = le_u32(i)?;
-        let (i, attribute_list) = all_consuming(many0(NotificationAttribute::parse))(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], GetNotificationAttributesResponse, Error> {
+        GetNotificationAttributesResponse::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `GetNotificationAttributesResponse` from a `&[u8]` that may
+    /// not hold the whole response yet
+    ///
+    /// Input that ends inside the header or inside an attribute returns
+    /// `nom::Err::Incomplete` with the number of bytes still needed. Input that ends
+    /// exactly between two attributes parses successfully, so compare the
+    /// `attribute_list` against the request to know whether more is expected.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// # use ancs::characteristics::data_source::GetNotificationAttributesResponse;
+    /// let bytes: Vec<u8> = vec![0, 1, 0, 0, 0, 1, 4, 0, 116, 101, 115, 116];
+    ///
+    /// assert_eq!(GetNotificationAttributesResponse::parse_streaming(&bytes[..3]), Err(nom::Err::Incomplete(nom::Needed::new(2))));
+    /// assert_eq!(GetNotificationAttributesResponse::parse_streaming(&bytes[..10]), Err(nom::Err::Incomplete(nom::Needed::new(2))));
+    ///
+    /// let (_, response) = GetNotificationAttributesResponse::parse_streaming(&bytes).unwrap();
+    /// assert_eq!(response.attribute_list[0].id, NotificationAttributeID::Title);
+    /// ```
+    pub fn parse_streaming(i: &[u8]) -> IResult<&[u8], GetNotificationAttributesResponse, Error> {
+        GetNotificationAttributesResponse::parse_with(i, ParseOptions::default().streaming())
+    }
+
+    /// Attempts to parse a `GetNotificationAttributesResponse` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], GetNotificationAttributesResponse, Error> {
+        let (i, command_id) = expect_command_id(i, CommandID::GetNotificationAttributes, options)?;
+        let (i, notification_uid) = parse::le_u32(i, options)?;
+        let (i, attribute_list) = parse_attributes(i, |i| NotificationAttribute::parse_with(i, options))?;
 
         Ok((
             i,
             GetNotificationAttributesResponse {
-                command_id: command_id,
-                notification_uid: notification_uid,
-                attribute_list: attribute_list,
+                command_id,
+                notification_uid,
+                attribute_list,
             },
         ))
     }
@@ -114,7 +141,9 @@ pub struct GetAppAttributesResponse {
     pub attribute_list: Vec<AppAttribute>,
 }
 
-impl From<GetAppAttributesResponse> for Vec<u8> {
+impl TryFrom<GetAppAttributesResponse> for Vec<u8> {
+    type Error = Error;
+
     /// Converts a `GetAppAttributesResponse` to a `Vec<u8>`
     /// 
     /// # Examples
@@ -161,38 +190,23 @@ impl From<GetAppAttributesResponse> for Vec<u8> {
     ///     115,
     ///     116
     /// ];
-    /// let data: Vec<u8> = response.into();
+    /// let data: Vec<u8> = response.try_into().unwrap();
     /// 
     /// assert_eq!(data, expected_data)
     /// ```
-    fn from(original: GetAppAttributesResponse) -> Vec<u8> {
+    fn try_from(original: GetAppAttributesResponse) -> Result<Vec<u8>, Self::Error> {
         let mut vec: Vec<u8> = Vec::new();
         
         // Convert all attributes to bytes
         let command_id: u8 = original.command_id.into();
-        let mut app_identifier: Vec<u8> = original.app_identifier.as_bytes().to_vec();
-        let mut attribute_ids: Vec<u8> = original
-            .attribute_list
-            .into_iter()
-            .map(|attribute| attribute.into())
-            .into_iter()
-            .flat_map(|att: Vec<u8>| att)
-            .collect();
-
-        // Rust strings are not null terminated by default 
-        // however it is possible that the user knows to insert
-        // a null terminated string of some kind this helps us
-        // ensure that all strings submitted to ANCS are null
-        // terminated UTF-8 byte strings.
-        if app_identifier.last().unwrap() != &0_u8 {
-            app_identifier.push(0);
-        }
+        let mut app_identifier: Vec<u8> = encode_app_identifier(original.app_identifier)?;
+        let mut attribute_ids: Vec<u8> = encode_attributes(original.attribute_list)?;
 
         vec.push(command_id);
-        vec.append(&mut app_identifier.into());
+        vec.append(&mut app_identifier);
         vec.append(&mut attribute_ids);
 
-        return vec;
+        Ok(vec)
     }
 }
 
@@ -244,18 +258,525 @@ impl GetAppAttributesResponse {
     ///    }
     /// ]);
     /// ```
-    pub fn parse(i: &[u8]) -> IResult<&[u8], GetAppAttributesResponse> {
-        let (i, command_id) = CommandID::parse(i)?;
-        let (i, app_identifier) = terminated(take_till(|b| b == 0), le_u8)(i)?;
-        let (i, attribute_list) = all_consuming(many0(AppAttribute::parse))(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], GetAppAttributesResponse, Error> {
+        GetAppAttributesResponse::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `GetAppAttributesResponse` from a `&[u8]` that may not hold
+    /// the whole response yet
+    ///
+    /// Behaves like `GetNotificationAttributesResponse::parse_streaming`; an app
+    /// identifier whose NULL terminator has not arrived yet needs at least one more byte.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::data_source::GetAppAttributesResponse;
+    /// let bytes: Vec<u8> = vec![1, 99, 111, 109, 0, 0, 4, 0, 84, 101, 115, 116];
+    ///
+    /// assert_eq!(GetAppAttributesResponse::parse_streaming(&bytes[..3]), Err(nom::Err::Incomplete(nom::Needed::new(1))));
+    /// assert_eq!(GetAppAttributesResponse::parse_streaming(&bytes[..9]), Err(nom::Err::Incomplete(nom::Needed::new(3))));
+    ///
+    /// let (_, response) = GetAppAttributesResponse::parse_streaming(&bytes).unwrap();
+    /// assert_eq!(response.app_identifier, "com");
+    /// ```
+    pub fn parse_streaming(i: &[u8]) -> IResult<&[u8], GetAppAttributesResponse, Error> {
+        GetAppAttributesResponse::parse_with(i, ParseOptions::default().streaming())
+    }
+
+    /// Attempts to parse a `GetAppAttributesResponse` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], GetAppAttributesResponse, Error> {
+        let (i, command_id) = expect_command_id(i, CommandID::GetAppAttributes, options)?;
+        let (i, app_identifier) = parse_app_identifier(i, options)?;
+        let (i, attribute_list) = parse_attributes(i, |i| AppAttribute::parse_with(i, options))?;
 
         Ok((
             i,
             GetAppAttributesResponse {
-                command_id: command_id,
-                app_identifier: String::from_utf8(app_identifier.to_vec()).unwrap(),
-                attribute_list: attribute_list,
+                command_id,
+                app_identifier,
+                attribute_list,
             },
         ))
     }
 }
+
+/// Any response the Notification Provider sends on the Data Source.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DataSourceResponse {
+    GetNotificationAttributes(GetNotificationAttributesResponse),
+    GetAppAttributes(GetAppAttributesResponse),
+}
+
+impl From<GetNotificationAttributesResponse> for DataSourceResponse {
+    fn from(original: GetNotificationAttributesResponse) -> DataSourceResponse {
+        DataSourceResponse::GetNotificationAttributes(original)
+    }
+}
+
+impl From<GetAppAttributesResponse> for DataSourceResponse {
+    fn from(original: GetAppAttributesResponse) -> DataSourceResponse {
+        DataSourceResponse::GetAppAttributes(original)
+    }
+}
+
+impl TryFrom<DataSourceResponse> for Vec<u8> {
+    type Error = Error;
+
+    /// Converts a `DataSourceResponse` to a `Vec<u8>`
+    fn try_from(original: DataSourceResponse) -> Result<Vec<u8>, Self::Error> {
+        match original {
+            DataSourceResponse::GetNotificationAttributes(response) => response.try_into(),
+            DataSourceResponse::GetAppAttributes(response) => response.try_into(),
+        }
+    }
+}
+
+impl DataSourceResponse {
+    /// Returns the `CommandID` this response answers.
+    pub fn command_id(&self) -> CommandID {
+        match self {
+            DataSourceResponse::GetNotificationAttributes(_) => CommandID::GetNotificationAttributes,
+            DataSourceResponse::GetAppAttributes(_) => CommandID::GetAppAttributes,
+        }
+    }
+
+    /// Attempts to parse a `DataSourceResponse` from a `&[u8]`, dispatching on the leading `CommandID`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::command::CommandID;
+    /// # use ancs::characteristics::data_source::DataSourceResponse;
+    /// let data: Vec<u8> = vec![1, 84, 101, 115, 116, 0, 0, 4, 0, 84, 101, 115, 116];
+    ///
+    /// match DataSourceResponse::parse(&data).unwrap().1 {
+    ///     DataSourceResponse::GetAppAttributes(response) => assert_eq!(response.app_identifier, "Test"),
+    ///     _ => panic!("expected an app attributes response"),
+    /// }
+    ///
+    /// // Performing an action never produces Data Source output
+    /// let data: Vec<u8> = vec![2, 255, 255, 255, 255, 0];
+    /// assert_eq!(
+    ///     DataSourceResponse::parse(&data).err(),
+    ///     Some(nom::Err::Failure(Error::NoDataSourceResponse(CommandID::PerformNotificationAction)))
+    /// );
+    /// ```
+    pub fn parse(i: &[u8]) -> IResult<&[u8], DataSourceResponse, Error> {
+        DataSourceResponse::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `DataSourceResponse` from a `&[u8]` using the given `ParseOptions`
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], DataSourceResponse, Error> {
+        let (_, command_id) = CommandID::parse_with(i, options)?;
+
+        match command_id {
+            CommandID::GetNotificationAttributes => GetNotificationAttributesResponse::parse_with(i, options)
+                .map(|(i, response)| (i, DataSourceResponse::GetNotificationAttributes(response))),
+            CommandID::GetAppAttributes => GetAppAttributesResponse::parse_with(i, options)
+                .map(|(i, response)| (i, DataSourceResponse::GetAppAttributes(response))),
+            CommandID::PerformNotificationAction => Err(nom::Err::Failure(Error::NoDataSourceResponse(command_id))),
+            CommandID::Unknown(id) => Err(nom::Err::Failure(Error::InvalidCommandID(id))),
+        }
+    }
+}
+
+/// The response a `DataSourceReassembler` is waiting for, derived from the
+/// Control Point request that was written.
+#[derive(Debug, PartialEq, Clone)]
+enum PendingResponse {
+    NotificationAttributes { notification_uid: u32, attribute_count: usize },
+    AppAttributes { app_identifier: Vec<u8>, attribute_count: usize },
+}
+
+/// Reassembles a Data Source response that iOS has split across several
+/// GATT notifications.
+///
+/// A reassembler is created from the outstanding Control Point request, so it
+/// knows which command, notification UID or app identifier and how many
+/// attributes to expect. Fragments are pushed in the order they were received
+/// and the typed response is returned once every requested attribute arrived.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DataSourceReassembler {
+    pending: PendingResponse,
+    buffer: Vec<u8>,
+    options: ParseOptions,
+}
+
+impl DataSourceReassembler {
+    /// Creates a `DataSourceReassembler` for the response to a `ControlPointRequest`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::attributes::command::CommandID;
+    /// # use ancs::characteristics::control_point::{ControlPointRequest, PerformNotificationActionRequest};
+    /// # use ancs::characteristics::data_source::DataSourceReassembler;
+    /// let request: ControlPointRequest = PerformNotificationActionRequest {
+    ///     notification_uid: 1,
+    ///     action_id: ActionID::Positive,
+    /// }.into();
+    ///
+    /// assert_eq!(
+    ///     DataSourceReassembler::new(&request),
+    ///     Err(Error::NoDataSourceResponse(CommandID::PerformNotificationAction))
+    /// );
+    /// ```
+    pub fn new(request: &ControlPointRequest) -> Result<DataSourceReassembler, Error> {
+        match request {
+            ControlPointRequest::GetNotificationAttributes(request) => {
+                Ok(DataSourceReassembler::for_notification_attributes(request))
+            },
+            ControlPointRequest::GetAppAttributes(request) => Ok(DataSourceReassembler::for_app_attributes(request)),
+            ControlPointRequest::PerformNotificationAction(_) => Err(Error::NoDataSourceResponse(request.command_id())),
+        }
+    }
+
+    /// Creates a `DataSourceReassembler` for the response to a `GetNotificationAttributesRequest`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::{GetNotificationAttributesRequest, RequestedAttribute};
+    /// # use ancs::characteristics::data_source::{DataSourceReassembler, DataSourceResponse};
+    /// let request = GetNotificationAttributesRequest {
+    ///     notification_uid: 1,
+    ///     attribute_ids: vec![RequestedAttribute::Title(16)],
+    /// };
+    /// let mut reassembler = DataSourceReassembler::for_notification_attributes(&request);
+    ///
+    /// assert_eq!(reassembler.push(&[0, 1, 0, 0, 0, 1, 4, 0, 116]).unwrap(), None);
+    /// assert_eq!(reassembler.is_complete(), false);
+    ///
+    /// let response = reassembler.push(&[101, 115, 116]).unwrap();
+    /// match response {
+    ///     Some(DataSourceResponse::GetNotificationAttributes(response)) => {
+    ///         assert_eq!(response.notification_uid, 1);
+    ///         assert_eq!(response.attribute_list[0].value, Some("test".to_string()));
+    ///     }
+    ///     _ => panic!("expected a complete response"),
+    /// }
+    /// ```
+    pub fn for_notification_attributes(request: &GetNotificationAttributesRequest) -> DataSourceReassembler {
+        DataSourceReassembler {
+            pending: PendingResponse::NotificationAttributes {
+                notification_uid: request.notification_uid,
+                attribute_count: request.attribute_ids.len(),
+            },
+            buffer: Vec::new(),
+            options: ParseOptions::default(),
+        }
+    }
+
+    /// Creates a `DataSourceReassembler` for the response to a `GetAppAttributesRequest`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// # use ancs::characteristics::control_point::GetAppAttributesRequest;
+    /// # use ancs::characteristics::data_source::{DataSourceReassembler, DataSourceResponse};
+    /// let request = GetAppAttributesRequest {
+    ///     app_identifier: "com.test".to_string(),
+    ///     attribute_ids: vec![AppAttributeID::DisplayName],
+    /// };
+    /// let mut reassembler = DataSourceReassembler::for_app_attributes(&request);
+    ///
+    /// assert_eq!(reassembler.push(&[1, 99, 111, 109, 46]).unwrap(), None);
+    /// let response = reassembler.push(&[116, 101, 115, 116, 0, 0, 4, 0, 84, 101, 115, 116]).unwrap();
+    ///
+    /// match response {
+    ///     Some(DataSourceResponse::GetAppAttributes(response)) => {
+    ///         assert_eq!(response.app_identifier, "com.test");
+    ///         assert_eq!(response.attribute_list[0].value, Some("Test".to_string()));
+    ///     }
+    ///     _ => panic!("expected a complete response"),
+    /// }
+    /// ```
+    pub fn for_app_attributes(request: &GetAppAttributesRequest) -> DataSourceReassembler {
+        let mut app_identifier = request.app_identifier.as_bytes().to_vec();
+
+        // Requests may carry their own NULL terminator, the response is
+        // compared without it.
+        if app_identifier.last() == Some(&0) {
+            app_identifier.pop();
+        }
+
+        DataSourceReassembler {
+            pending: PendingResponse::AppAttributes {
+                app_identifier,
+                attribute_count: request.attribute_ids.len(),
+            },
+            buffer: Vec::new(),
+            options: ParseOptions::default(),
+        }
+    }
+
+    /// Sets the `ParseOptions` used to parse the complete response.
+    pub fn with_options(mut self, options: ParseOptions) -> DataSourceReassembler {
+        self.options = options;
+        self
+    }
+
+    /// Appends a Data Source fragment, returning the typed response once every
+    /// requested attribute has been received.
+    ///
+    /// A fragment whose header does not match the outstanding command, or that
+    /// carries bytes past the last requested attribute, is rejected with a
+    /// `Error` and leaves the buffered data untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::{GetNotificationAttributesRequest, RequestedAttribute};
+    /// # use ancs::Error;
+    /// # use ancs::characteristics::data_source::DataSourceReassembler;
+    /// let request = GetNotificationAttributesRequest {
+    ///     notification_uid: 1,
+    ///     attribute_ids: vec![RequestedAttribute::AppIdentifier],
+    /// };
+    /// let mut reassembler = DataSourceReassembler::for_notification_attributes(&request);
+    ///
+    /// // A response for notification 2 does not belong to this request
+    /// assert_eq!(reassembler.push(&[0, 2, 0, 0, 0]), Err(Error::UnexpectedNotificationUID(2)));
+    /// // A response to another command does not belong to it either
+    /// assert_eq!(reassembler.push(&[1, 0]), Err(Error::UnexpectedCommandID(1)));
+    /// assert_eq!(reassembler.is_empty(), true);
+    /// ```
+    pub fn push(&mut self, fragment: &[u8]) -> Result<Option<DataSourceResponse>, Error> {
+        if self.is_complete() {
+            return Err(Error::AlreadyComplete);
+        }
+
+        let previous = self.buffer.len();
+        self.buffer.extend_from_slice(fragment);
+
+        let result = match self.pending.scan(&self.buffer) {
+            Ok(Some(length)) if length < self.buffer.len() => Err(Error::TrailingBytes(self.buffer.len() - length)),
+            Ok(Some(_)) => self.pending.parse(&self.buffer, self.options).map(Some),
+            Ok(None) => Ok(None),
+            Err(error) => Err(error),
+        };
+
+        // A rejected fragment leaves the buffered data as it was.
+        if result.is_err() {
+            self.buffer.truncate(previous);
+        }
+
+        result
+    }
+
+    /// Determines if every requested attribute has been received.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.pending.scan(&self.buffer), Ok(Some(_)))
+    }
+
+    /// Returns the number of bytes buffered so far.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Determines if no fragment has been received yet.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+}
+
+impl PendingResponse {
+    /// Walks the buffered bytes without copying them, returning the length of the
+    /// complete response once every requested attribute is present.
+    fn scan(&self, buffer: &[u8]) -> Result<Option<usize>, Error> {
+        let (expected_command, attribute_count) = match self {
+            PendingResponse::NotificationAttributes { attribute_count, .. } => {
+                (CommandID::GetNotificationAttributes, *attribute_count)
+            },
+            PendingResponse::AppAttributes { attribute_count, .. } => (CommandID::GetAppAttributes, *attribute_count),
+        };
+
+        let command_id = match buffer.first() {
+            Some(command_id) => *command_id,
+            None => return Ok(None),
+        };
+
+        if command_id != u8::from(expected_command) {
+            return Err(Error::UnexpectedCommandID(command_id));
+        }
+
+        let mut offset = match self {
+            PendingResponse::NotificationAttributes { notification_uid, .. } => {
+                let uid: [u8; 4] = match buffer.get(1..5) {
+                    Some(uid) => uid.try_into().unwrap_or_default(),
+                    None => return Ok(None),
+                };
+                let uid = u32::from_le_bytes(uid);
+
+                if uid != *notification_uid {
+                    return Err(Error::UnexpectedNotificationUID(uid));
+                }
+
+                5
+            },
+            PendingResponse::AppAttributes { app_identifier, .. } => {
+                let received = &buffer[1..];
+                let terminator = received.iter().position(|&b| b == 0);
+                let identifier = &received[..terminator.unwrap_or(received.len())];
+
+                // Compare as much of the identifier as has arrived so that
+                // a foreign response is caught on its first fragment.
+                if !app_identifier.starts_with(identifier)
+                    || (terminator.is_some() && identifier.len() != app_identifier.len())
+                {
+                    return Err(Error::UnexpectedAppIdentifier);
+                }
+
+                match terminator {
+                    Some(terminator) => terminator + 2,
+                    None => return Ok(None),
+                }
+            },
+        };
+
+        for _ in 0..attribute_count {
+            let length = match buffer.get(offset + 1..offset + 3) {
+                Some(length) => u16::from_le_bytes([length[0], length[1]]) as usize,
+                None => return Ok(None),
+            };
+
+            offset += 3 + length;
+
+            if buffer.len() < offset {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(offset))
+    }
+
+    fn parse(&self, buffer: &[u8], options: ParseOptions) -> Result<DataSourceResponse, Error> {
+        match self {
+            PendingResponse::NotificationAttributes { .. } => GetNotificationAttributesResponse::parse_with(buffer, options)
+                .map(|(_, response)| DataSourceResponse::GetNotificationAttributes(response))
+                .map_err(Error::from),
+            PendingResponse::AppAttributes { .. } => GetAppAttributesResponse::parse_with(buffer, options)
+                .map(|(_, response)| DataSourceResponse::GetAppAttributes(response))
+                .map_err(Error::from),
+        }
+    }
+}
+
+/// Parses attributes until the input is exhausted. Unlike `many0` this never treats
+/// an incomplete attribute as the end of the list, so streaming parsers can report
+/// how many bytes are missing.
+fn parse_attributes<'a, T, F>(mut i: &'a [u8], mut parser: F) -> IResult<&'a [u8], Vec<T>, Error>
+where
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], T, Error>,
+{
+    let mut attributes: Vec<T> = Vec::new();
+
+    while !i.is_empty() {
+        let (remaining, attribute) = parser(i)?;
+        attributes.push(attribute);
+        i = remaining;
+    }
+
+    Ok((i, attributes))
+}
+
+/// Encodes every attribute of a response in order.
+fn encode_attributes<T>(attributes: Vec<T>) -> Result<Vec<u8>, Error>
+where
+    Vec<u8>: TryFrom<T, Error = Error>,
+{
+    let mut vec: Vec<u8> = Vec::new();
+
+    for attribute in attributes {
+        vec.extend(Vec::<u8>::try_from(attribute)?);
+    }
+
+    Ok(vec)
+}
+
+/// Splits encoded Data Source responses into GATT notification payloads the
+/// way iOS does: every payload but the last fills the `MTU - 3` bytes an ATT
+/// notification can carry. This is the counterpart of `DataSourceReassembler`.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::command::CommandID;
+/// # use ancs::attributes::NotificationAttribute;
+/// # use ancs::attributes::notification::NotificationAttributeID;
+/// # use ancs::characteristics::control_point::{GetNotificationAttributesRequest, RequestedAttribute};
+/// # use ancs::characteristics::data_source::{
+/// #     DataSourceFragmenter, DataSourceReassembler, DataSourceResponse, GetNotificationAttributesResponse,
+/// # };
+/// let message = "A message far longer than a single notification payload".to_string();
+/// let response = GetNotificationAttributesResponse {
+///     command_id: CommandID::GetNotificationAttributes,
+///     notification_uid: 1,
+///     attribute_list: vec![NotificationAttribute {
+///         id: NotificationAttributeID::Message,
+///         length: message.len() as u16,
+///         value: Some(message),
+///     }],
+/// };
+///
+/// let fragments = DataSourceFragmenter::default().encode(response.clone()).unwrap();
+/// assert_eq!(fragments.iter().map(Vec::len).collect::<Vec<_>>(), vec![20, 20, 20, 3]);
+///
+/// let mut reassembler = DataSourceReassembler::for_notification_attributes(&GetNotificationAttributesRequest {
+///     notification_uid: 1,
+///     attribute_ids: vec![RequestedAttribute::Message(64)],
+/// });
+/// let mut reassembled = None;
+/// for fragment in &fragments {
+///     reassembled = reassembler.push(fragment).unwrap();
+/// }
+///
+/// assert_eq!(reassembled, Some(DataSourceResponse::GetNotificationAttributes(response)));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DataSourceFragmenter {
+    mtu: u16,
+}
+
+impl Default for DataSourceFragmenter {
+    fn default() -> Self {
+        DataSourceFragmenter::new(DEFAULT_ATT_MTU)
+    }
+}
+
+impl DataSourceFragmenter {
+    /// Creates a `DataSourceFragmenter` for the given ATT MTU. MTUs below
+    /// `DEFAULT_ATT_MTU`, which no connection can negotiate, are raised to it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::data_source::DataSourceFragmenter;
+    /// assert_eq!(DataSourceFragmenter::new(185).payload_size(), 182);
+    /// assert_eq!(DataSourceFragmenter::new(5).mtu(), 23);
+    /// ```
+    pub fn new(mtu: u16) -> DataSourceFragmenter {
+        DataSourceFragmenter {
+            mtu: mtu.max(DEFAULT_ATT_MTU),
+        }
+    }
+
+    /// Returns the ATT MTU fragments are sized for.
+    pub fn mtu(&self) -> u16 {
+        self.mtu
+    }
+
+    /// Returns the most bytes a single fragment carries.
+    pub fn payload_size(&self) -> usize {
+        usize::from(self.mtu - ATT_NOTIFICATION_HEADER)
+    }
+
+    /// Splits already encoded bytes into fragments, in the order they are sent.
+    pub fn fragment(&self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        bytes.chunks(self.payload_size()).map(<[u8]>::to_vec).collect()
+    }
+
+    /// Encodes a response and splits it into fragments, in the order they are sent.
+    pub fn encode(&self, response: impl Into<DataSourceResponse>) -> Result<Vec<Vec<u8>>, Error> {
+        let bytes: Vec<u8> = response.into().try_into()?;
+
+        Ok(self.fragment(&bytes))
+    }
+}
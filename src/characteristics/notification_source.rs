@@ -1,12 +1,14 @@
 use crate::attributes::category::*;
 use crate::attributes::event::*;
 
-use nom::{multi::count, number::complete::le_u8, IResult};
+use crate::{parse, Error, ParseOptions};
+
+use nom::IResult;
 use uuid::{uuid, Uuid};
 
 pub const NOTIFICATION_SOURCE_UUID: Uuid = uuid!("9FBF120D-6301-42D9-8C58-25E699A21DBD");
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Notification {
     pub event_id: EventID,
     pub event_flags: EventFlag,
@@ -42,21 +44,43 @@ impl Notification {
     /// assert_eq!(parsed_notification.category_count, 0);
     /// assert_eq!(parsed_notification.notification_uid, 4294967295_u32);
     /// ```
-    pub fn parse(i: &[u8]) -> IResult<&[u8], Notification> {
-        let (i, event_id) = EventID::parse(i)?;
-        let (i, event_flags) = EventFlag::parse(i)?;
-        let (i, category_id) = CategoryID::parse(i)?;
-        let (i, category_count) = le_u8(i)?;
-        let (i, notification_uid) = count(le_u8, 4)(i)?;
+    pub fn parse(i: &[u8]) -> IResult<&[u8], Notification, Error> {
+        Notification::parse_with(i, ParseOptions::default())
+    }
+
+    /// Attempts to parse a `Notification` from a `&[u8]` using the given `ParseOptions`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// // A category added by a newer iOS release
+    /// let bytes: [u8; 8] = [0, 0, 12, 1, 7, 0, 0, 0];
+    ///
+    /// assert!(Notification::parse(&bytes).is_err());
+    ///
+    /// let (_, notification) = Notification::parse_with(&bytes, ParseOptions::lenient()).unwrap();
+    /// assert_eq!(notification.category_id, CategoryID::Unknown(12));
+    ///
+    /// let encoded: [u8; 8] = notification.into();
+    /// assert_eq!(encoded, bytes);
+    /// ```
+    pub fn parse_with(i: &[u8], options: ParseOptions) -> IResult<&[u8], Notification, Error> {
+        let (i, event_id) = EventID::parse_with(i, options)?;
+        let (i, event_flags) = EventFlag::parse_with(i, options)?;
+        let (i, category_id) = CategoryID::parse_with(i, options)?;
+        let (i, category_count) = parse::le_u8(i, options)?;
+        let (i, notification_uid) = parse::le_u32(i, options)?;
 
         Ok((
             i,
             Notification {
-                event_id: event_id,
-                event_flags: event_flags,
-                category_id: category_id,
+                event_id,
+                event_flags,
+                category_id,
                 category_count,
-                notification_uid: u32::from_le_bytes(notification_uid.try_into().unwrap()),
+                notification_uid,
             },
         ))
     }
@@ -84,21 +108,20 @@ impl From<Notification> for [u8; 8] {
     ///
     ///assert_eq!(notification_bytes, expected_bytes)
     /// ```
-
     fn from(original: Notification) -> [u8; 8] {
         let mut bytes: [u8; 8] = [0; 8];
         let uid_as_u8 = original.notification_uid.to_le_bytes();
 
-        bytes[0] = original.event_id as u8;
+        bytes[0] = original.event_id.into();
         bytes[1] = u8::from(original.event_flags);
-        bytes[2] = original.category_id as u8;
-        bytes[3] = original.category_count as u8;
+        bytes[2] = original.category_id.into();
+        bytes[3] = original.category_count;
 
         bytes[4] = uid_as_u8[0];
         bytes[5] = uid_as_u8[1];
         bytes[6] = uid_as_u8[2];
         bytes[7] = uid_as_u8[3];
 
-        return bytes;
+        bytes
     }
 }
@@ -0,0 +1,773 @@
+//! ## Client
+//!
+//! A transport-agnostic Notification Consumer built on the types in `attributes`
+//! and `characteristics`. The `AncsClient` performs no I/O of its own: the caller
+//! feeds it the bytes received on the Notification Source and Data Source
+//! characteristics, writes the bytes it hands out to the Control Point, and
+//! collects the high-level `Event`s it produces. This keeps the protocol logic
+//! usable with any Bluetooth stack and fully testable without a radio.
+//!
+//! Control Point commands are serialized through a `queue::CommandQueue`, which
+//! correlates Data Source replies with the outstanding command and times out
+//! commands that are never answered using the client's `clock::Clock`. The
+//! `store::NotificationStore` keeps the current state of every notification from
+//! the `Event`s the client produces, and the client resolves app display names
+//! through an `app_names::AppNameCache`. Which attributes are fetched for each
+//! notification is decided by a `policy::FetchPolicy`, and when they are fetched
+//! by a `scheduler::FetchScheduler`. Phone calls can be followed from the same
+//! `Event`s with a `calls::CallTracker`, and filtered with a `rules::RuleSet`.
+//!
+//! With the `async` feature, `async_client::AsyncClient` drives the client over an
+//! async transport and exposes its `Event`s as a `Stream`.
+//!
+pub mod actions;
+pub mod app_names;
+#[cfg(feature = "async")]
+pub mod async_client;
+pub mod calls;
+pub mod clock;
+pub mod policy;
+pub mod queue;
+pub mod rules;
+pub mod scheduler;
+pub mod store;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::attributes::action::ActionID;
+use crate::attributes::app::AppAttributeID;
+use crate::attributes::event::EventID;
+use crate::attributes::notification::NotificationAttributeID;
+use crate::attributes::NotificationAttribute;
+use crate::characteristics::control_point::*;
+use crate::characteristics::data_source::*;
+use crate::characteristics::notification_source::*;
+use crate::{Error, ParseOptions};
+
+use app_names::{AppName, AppNameCache, Lookup};
+use clock::{Clock, SystemClock};
+use policy::{FetchPolicy, FetchRule};
+use queue::{CommandQueue, Completion};
+use scheduler::FetchScheduler;
+use store::TrackedNotification;
+
+use uuid::Uuid;
+
+/// The characteristics a client must enable notifications on, in the order they
+/// should be enabled. The Data Source comes first so that no response to a
+/// request triggered by the first Notification Source event can be missed.
+pub const SUBSCRIPTIONS: [Uuid; 2] = [DATA_SOURCE_UUID, NOTIFICATION_SOURCE_UUID];
+
+/// The attributes fetched for every added or modified notification by the
+/// default `FetchPolicy`.
+pub const DEFAULT_ATTRIBUTES: [RequestedAttribute; 5] = [
+    RequestedAttribute::AppIdentifier,
+    RequestedAttribute::Title(u8::MAX as u16),
+    RequestedAttribute::Message(u8::MAX as u16),
+    RequestedAttribute::Date,
+    RequestedAttribute::MessageSize,
+];
+
+/// A high-level event produced by an `AncsClient`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    /// A notification was added and its attributes were fetched, unless the
+    /// `FetchPolicy` skipped it. If the fetch failed or timed out, this follows
+    /// the `CommandFailed` or `CommandTimedOut` event with the attributes that
+    /// did arrive, if any.
+    NotificationAdded {
+        notification: Notification,
+        attributes: Vec<NotificationAttribute>,
+    },
+    /// A notification was modified and its attributes were fetched again, unless
+    /// the `FetchPolicy` skipped it. A failed fetch is reported as for
+    /// `NotificationAdded`.
+    NotificationModified {
+        notification: Notification,
+        attributes: Vec<NotificationAttribute>,
+    },
+    /// A notification was removed.
+    NotificationRemoved(Notification),
+    /// The response to an explicit `AncsClient::get_notification_attributes` call.
+    NotificationAttributes(GetNotificationAttributesResponse),
+    /// The response to an explicit `AncsClient::get_app_attributes` call.
+    AppAttributes(GetAppAttributesResponse),
+    /// The display name requested by `AncsClient::app_name` arrived.
+    AppNameResolved {
+        app_identifier: String,
+        name: AppName,
+    },
+    /// The Notification Provider accepted an action.
+    ActionPerformed(PerformNotificationActionRequest),
+    /// The Notification Provider rejected a Control Point write.
+    CommandFailed(ControlPointOutcome<ControlPointRequest>),
+    /// The Notification Provider did not answer a command in time.
+    CommandTimedOut(ControlPointRequest),
+}
+
+/// Why a command was queued, which decides the `Event` its response becomes.
+#[derive(Debug, PartialEq, Clone)]
+enum Purpose {
+    /// Fetching the attributes of a Notification Source event.
+    Fetch(Notification),
+    /// Fetching the attributes a per-app `FetchRule` adds to those already fetched.
+    FollowUp(Notification, Vec<NotificationAttribute>),
+    /// A request made explicitly by the caller.
+    Request,
+    /// Looking up an app's display name for the `AppNameCache`.
+    AppName,
+    /// A fetch whose notification was removed while its attributes were in
+    /// flight. Its outcome, whether a response, a rejection or a timeout, is
+    /// dropped without an `Event`.
+    Cancelled,
+}
+
+/// The `AncsClient` type. See [the module level documentation](index.html) for more.
+///
+/// ANCS processes one Control Point command at a time, so the client holds back
+/// further writes until the current command has been answered or has timed out.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+/// # use ancs::characteristics::notification_source::{Notification, NOTIFICATION_SOURCE_UUID};
+/// # use ancs::client::{AncsClient, Event};
+/// # use ancs::characteristics::control_point::RequestedAttribute;
+/// let mut client = AncsClient::new().with_attributes(vec![RequestedAttribute::Title(16)]);
+///
+/// // iOS reports a new notification on the Notification Source
+/// let added: [u8; 8] = Notification {
+///     event_id: EventID::NotificationAdded,
+///     event_flags: EventFlag::empty(),
+///     category_id: CategoryID::Social,
+///     category_count: 1,
+///     notification_uid: 7,
+/// }.into();
+/// client.handle_notification(NOTIFICATION_SOURCE_UUID, &added).unwrap();
+///
+/// // The client asks for its attributes on the Control Point
+/// assert_eq!(client.poll_transmit(), Some(vec![0, 7, 0, 0, 0, 1, 16, 0]));
+/// client.handle_write_result(Ok(()));
+///
+/// // iOS answers on the Data Source
+/// client.handle_notification(DATA_SOURCE_UUID, &[0, 7, 0, 0, 0, 1, 2, 0, 72, 105]).unwrap();
+///
+/// match client.poll_event() {
+///     Some(Event::NotificationAdded { notification, attributes }) => {
+///         assert_eq!(notification.notification_uid, 7);
+///         assert_eq!(attributes[0].value, Some("Hi".to_string()));
+///     },
+///     event => panic!("unexpected event {:?}", event),
+/// }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct AncsClient<C = SystemClock> {
+    policy: FetchPolicy,
+    options: ParseOptions,
+    queue: CommandQueue<Purpose, C>,
+    scheduler: FetchScheduler,
+    app_names: AppNameCache,
+    events: VecDeque<Event>,
+}
+
+impl Default for AncsClient {
+    fn default() -> Self {
+        AncsClient::new()
+    }
+}
+
+impl AncsClient {
+    /// Creates an `AncsClient` that fetches `DEFAULT_ATTRIBUTES` for every notification.
+    pub fn new() -> AncsClient {
+        AncsClient::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock> AncsClient<C> {
+    /// Creates an `AncsClient` that reads time from `clock` to time out commands.
+    pub fn with_clock(clock: C) -> AncsClient<C> {
+        AncsClient {
+            policy: FetchPolicy::default(),
+            options: ParseOptions::default(),
+            queue: CommandQueue::new(clock),
+            scheduler: FetchScheduler::new(),
+            app_names: AppNameCache::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Sets the attributes fetched for every added or modified notification
+    /// without a more specific `FetchRule`.
+    pub fn with_attributes(mut self, attributes: Vec<RequestedAttribute>) -> AncsClient<C> {
+        self.policy = self.policy.with_default(FetchRule::Fetch(attributes));
+        self
+    }
+
+    /// Sets the `FetchPolicy` deciding what is fetched for every added or modified notification.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::{Notification, NOTIFICATION_SOURCE_UUID};
+    /// # use ancs::client::{AncsClient, Event};
+    /// # use ancs::client::policy::{FetchPolicy, FetchRule};
+    /// let policy = FetchPolicy::default().with_category(CategoryID::Location, FetchRule::Skip);
+    /// let mut client = AncsClient::new().with_policy(policy);
+    ///
+    /// let added: [u8; 8] = Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::empty(),
+    ///     category_id: CategoryID::Location,
+    ///     category_count: 1,
+    ///     notification_uid: 2,
+    /// }.into();
+    /// client.handle_notification(NOTIFICATION_SOURCE_UUID, &added).unwrap();
+    ///
+    /// // Nothing is fetched, the notification is reported straight away
+    /// assert_eq!(client.poll_transmit(), None);
+    /// assert!(matches!(client.poll_event(), Some(Event::NotificationAdded { attributes, .. }) if attributes.is_empty()));
+    /// ```
+    pub fn with_policy(mut self, policy: FetchPolicy) -> AncsClient<C> {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the `ParseOptions` used for everything received from the Notification Provider.
+    pub fn with_options(mut self, options: ParseOptions) -> AncsClient<C> {
+        self.options = options;
+        self.queue = self.queue.with_options(options);
+        self
+    }
+
+    /// Sets the `FetchScheduler` ordering the fetches for Notification Source events.
+    pub fn with_scheduler(mut self, scheduler: FetchScheduler) -> AncsClient<C> {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Sets the `AppNameCache` used by `app_name`, for example one restored from
+    /// a previous connection. Lookups that were in flight are forgotten.
+    pub fn with_app_names(mut self, mut app_names: AppNameCache) -> AncsClient<C> {
+        app_names.clear_pending();
+        self.app_names = app_names;
+        self
+    }
+
+    /// Sets how long a command may remain unanswered before `Event::CommandTimedOut`.
+    pub fn with_timeout(mut self, timeout: Duration) -> AncsClient<C> {
+        self.queue = self.queue.with_timeout(timeout);
+        self
+    }
+
+    /// Handles a GATT notification received on the Notification Source or Data Source.
+    ///
+    /// Bytes that cannot be parsed, or Data Source fragments that do not belong to
+    /// the outstanding command, are rejected without changing the client's state.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+    /// # use ancs::characteristics::notification_source::NOTIFICATION_SOURCE_UUID;
+    /// # use ancs::client::AncsClient;
+    /// let mut client = AncsClient::new();
+    ///
+    /// assert_eq!(client.handle_notification(NOTIFICATION_SOURCE_UUID, &[0, 0, 0]), Err(Error::Truncated));
+    /// assert_eq!(client.handle_notification(DATA_SOURCE_UUID, &[0, 1, 0, 0, 0]), Err(Error::NoOutstandingCommand));
+    /// ```
+    ///
+    /// A notification removed while its attributes are being fetched cancels the
+    /// fetch, and whatever becomes of it is not reported:
+    /// ```
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::control_point::ControlPointError;
+    /// # use ancs::characteristics::notification_source::{Notification, NOTIFICATION_SOURCE_UUID};
+    /// # use ancs::client::{AncsClient, Event};
+    /// let mut client = AncsClient::new();
+    /// let mut notification = Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::empty(),
+    ///     category_id: CategoryID::Email,
+    ///     category_count: 1,
+    ///     notification_uid: 3,
+    /// };
+    /// let added: [u8; 8] = notification.into();
+    /// client.handle_notification(NOTIFICATION_SOURCE_UUID, &added).unwrap();
+    /// client.poll_transmit().unwrap();
+    ///
+    /// notification.event_id = EventID::NotificationRemoved;
+    /// notification.category_count = 0;
+    /// let removed: [u8; 8] = notification.into();
+    /// client.handle_notification(NOTIFICATION_SOURCE_UUID, &removed).unwrap();
+    ///
+    /// // iOS no longer knows the UID and rejects the fetch
+    /// client.handle_write_result(Err(ControlPointError::InvalidParameter));
+    ///
+    /// assert_eq!(client.poll_event(), Some(Event::NotificationRemoved(notification)));
+    /// assert_eq!(client.poll_event(), None);
+    /// assert_eq!(client.is_idle(), true);
+    /// ```
+    pub fn handle_notification(&mut self, characteristic: Uuid, bytes: &[u8]) -> Result<(), Error> {
+        match characteristic {
+            NOTIFICATION_SOURCE_UUID => {
+                let (_, notification) = Notification::parse_with(bytes, self.options)?;
+                self.handle_notification_source(notification);
+                Ok(())
+            },
+            DATA_SOURCE_UUID => self.handle_data_source(bytes),
+            characteristic => Err(Error::UnknownCharacteristic(characteristic)),
+        }
+    }
+
+    /// Handles the result of the last Control Point write handed out by `poll_transmit`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::characteristics::control_point::ControlPointError;
+    /// # use ancs::client::{AncsClient, Event};
+    /// let mut client = AncsClient::new();
+    ///
+    /// client.perform_notification_action(3, ActionID::Negative);
+    /// assert_eq!(client.poll_transmit(), Some(vec![2, 3, 0, 0, 0, 1]));
+    /// client.handle_write_result(Err(ControlPointError::InvalidParameter));
+    ///
+    /// match client.poll_event() {
+    ///     Some(Event::CommandFailed(outcome)) => assert_eq!(outcome.is_stale(), true),
+    ///     event => panic!("unexpected event {:?}", event),
+    /// }
+    /// ```
+    ///
+    /// A notification whose attribute fetch fails is still reported, without attributes:
+    /// ```
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::control_point::ControlPointError;
+    /// # use ancs::characteristics::notification_source::{Notification, NOTIFICATION_SOURCE_UUID};
+    /// # use ancs::client::{AncsClient, Event};
+    /// let mut client = AncsClient::new();
+    /// let notification = Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::empty(),
+    ///     category_id: CategoryID::Email,
+    ///     category_count: 1,
+    ///     notification_uid: 5,
+    /// };
+    /// let bytes: [u8; 8] = notification.into();
+    ///
+    /// client.handle_notification(NOTIFICATION_SOURCE_UUID, &bytes).unwrap();
+    /// client.poll_transmit().unwrap();
+    /// client.handle_write_result(Err(ControlPointError::InvalidParameter));
+    ///
+    /// assert!(matches!(client.poll_event(), Some(Event::CommandFailed(_))));
+    /// assert_eq!(
+    ///     client.poll_event(),
+    ///     Some(Event::NotificationAdded { notification, attributes: vec![] })
+    /// );
+    /// ```
+    pub fn handle_write_result(&mut self, result: Result<(), ControlPointError>) {
+        if let Some(completion) = self.queue.handle_write_result(result) {
+            self.complete(completion);
+        }
+    }
+
+    /// Returns the next Control Point write to send, if the previous command has completed.
+    ///
+    /// Every write handed out must be followed by a call to `handle_write_result`
+    /// before `poll_transmit` is called again.
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        // Fetches are released as late as possible so that urgent ones can overtake.
+        while let Some((notification, request)) = self.scheduler.poll() {
+            self.enqueue(request.into(), Purpose::Fetch(notification));
+        }
+
+        self.queue.poll_transmit()
+    }
+
+    /// Returns when `handle_timeout` should next be called, if a command is outstanding.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        self.queue.poll_timeout()
+    }
+
+    /// Times out the outstanding command if it has not been answered in time,
+    /// producing an `Event::CommandTimedOut`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::time::Duration;
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// # use ancs::client::{AncsClient, Event};
+    /// # use ancs::client::clock::ManualClock;
+    /// let clock = ManualClock::new();
+    /// let mut client = AncsClient::with_clock(clock.clone()).with_timeout(Duration::from_secs(10));
+    ///
+    /// client.get_app_attributes("com.apple.mobilemail".to_string(), vec![AppAttributeID::DisplayName]).unwrap();
+    /// client.poll_transmit().unwrap();
+    /// client.handle_write_result(Ok(()));
+    ///
+    /// clock.advance(Duration::from_secs(9));
+    /// client.handle_timeout();
+    /// assert_eq!(client.poll_event(), None);
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// client.handle_timeout();
+    /// assert!(matches!(client.poll_event(), Some(Event::CommandTimedOut(_))));
+    /// assert_eq!(client.is_idle(), true);
+    /// ```
+    pub fn handle_timeout(&mut self) {
+        if let Some(completion) = self.queue.handle_timeout() {
+            self.complete(completion);
+        }
+    }
+
+    /// Handles expired commands and returns the next Control Point write, the
+    /// first half of a driver's step. Shared by `transport::drive` and the
+    /// `AsyncClient`.
+    pub(crate) fn poll_write(&mut self) -> Option<Vec<u8>> {
+        self.handle_timeout();
+        self.poll_transmit()
+    }
+
+    /// Handles the result of a write returned by `poll_write`, handing back the
+    /// transport's own error, which leaves the command outstanding.
+    pub(crate) fn handle_write<E>(&mut self, result: Result<(), WriteError<E>>) -> Result<(), E> {
+        match result {
+            Ok(()) => self.handle_write_result(Ok(())),
+            Err(WriteError::Rejected(error)) => self.handle_write_result(Err(error)),
+            Err(WriteError::Transport(error)) => return Err(error),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the next `Event`, if any.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// Queues a `GetNotificationAttributesRequest`, answered with `Event::NotificationAttributes`.
+    pub fn get_notification_attributes(&mut self, notification_uid: u32, attribute_ids: Vec<RequestedAttribute>) {
+        let request = GetNotificationAttributesRequest {
+            notification_uid,
+            attribute_ids,
+        };
+
+        self.enqueue(request.into(), Purpose::Request);
+    }
+
+    /// Queues a `GetAppAttributesRequest`, answered with `Event::AppAttributes`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// # use ancs::client::AncsClient;
+    /// let mut client = AncsClient::new();
+    ///
+    /// assert_eq!(client.get_app_attributes(String::new(), vec![AppAttributeID::DisplayName]), Err(Error::InvalidAppIdentifier));
+    /// ```
+    pub fn get_app_attributes(&mut self, app_identifier: String, attribute_ids: Vec<AppAttributeID>) -> Result<(), Error> {
+        let request = GetAppAttributesRequest {
+            app_identifier,
+            attribute_ids,
+        };
+
+        self.queue.push(request.into(), Purpose::Request)
+    }
+
+    /// Returns the display name of an app if it was already looked up. Otherwise
+    /// a lookup is queued, unless one is already in flight, and
+    /// `Event::AppNameResolved` follows once it has been answered.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+    /// # use ancs::client::{AncsClient, Event};
+    /// # use ancs::client::app_names::AppName;
+    /// let mut client = AncsClient::new();
+    ///
+    /// assert_eq!(client.app_name("a.b"), Ok(None));
+    /// assert_eq!(client.app_name("a.b"), Ok(None));
+    /// assert_eq!(client.poll_transmit(), Some(vec![1, 97, 46, 98, 0, 0]));
+    /// assert_eq!(client.poll_transmit(), None);
+    /// client.handle_write_result(Ok(()));
+    ///
+    /// client.handle_notification(DATA_SOURCE_UUID, &[1, 97, 46, 98, 0, 0, 2, 0, 65, 66]).unwrap();
+    ///
+    /// assert!(matches!(client.poll_event(), Some(Event::AppNameResolved { .. })));
+    /// assert_eq!(client.app_name("a.b"), Ok(Some(AppName::Known("AB".to_string()))));
+    /// assert_eq!(client.is_idle(), true);
+    /// ```
+    pub fn app_name(&mut self, app_identifier: &str) -> Result<Option<AppName>, Error> {
+        match self.app_names.lookup(app_identifier) {
+            Lookup::Cached(name) => Ok(Some(name)),
+            Lookup::Pending => Ok(None),
+            Lookup::Request(request) => match self.queue.push(request.into(), Purpose::AppName) {
+                Ok(()) => Ok(None),
+                Err(error) => {
+                    self.app_names.handle_failure(app_identifier);
+                    Err(error)
+                },
+            },
+        }
+    }
+
+    /// Returns the `AppNameCache`, for example to export it before disconnecting.
+    pub fn app_names(&self) -> &AppNameCache {
+        &self.app_names
+    }
+
+    /// Returns the `Clock` commands are timed out with.
+    pub fn clock(&self) -> &C {
+        self.queue.clock()
+    }
+
+    /// Queues a `PerformNotificationActionRequest`, answered with `Event::ActionPerformed`.
+    pub fn perform_notification_action(&mut self, notification_uid: u32, action_id: ActionID) {
+        let request = PerformNotificationActionRequest {
+            notification_uid,
+            action_id,
+        };
+
+        self.enqueue(request.into(), Purpose::Request);
+    }
+
+    /// Queues the action on a tracked notification, after checking that the
+    /// notification offers it. Answered with `Event::ActionPerformed`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::AncsClient;
+    /// # use ancs::client::store::TrackedNotification;
+    /// let mut client = AncsClient::new();
+    /// let tracked = TrackedNotification::new(Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::NegativeAction,
+    ///     category_id: CategoryID::Email,
+    ///     category_count: 1,
+    ///     notification_uid: 5,
+    /// });
+    ///
+    /// assert_eq!(client.perform_action(&tracked, ActionID::Positive), Err(Error::ActionUnavailable(ActionID::Positive)));
+    /// assert_eq!(client.is_idle(), true);
+    ///
+    /// assert_eq!(client.perform_action(&tracked, ActionID::Negative), Ok(()));
+    /// assert_eq!(client.poll_transmit(), Some(vec![2, 5, 0, 0, 0, 1]));
+    /// ```
+    pub fn perform_action(&mut self, tracked: &TrackedNotification, action_id: ActionID) -> Result<(), Error> {
+        let request = tracked.action_request(action_id)?;
+        self.enqueue(request.into(), Purpose::Request);
+
+        Ok(())
+    }
+
+    /// Determines if no command is queued or awaiting a response.
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_idle() && self.scheduler.is_empty()
+    }
+
+    /// Queues a request that always encodes, so pushing it cannot fail.
+    fn enqueue(&mut self, request: ControlPointRequest, purpose: Purpose) {
+        // Only `GetAppAttributesRequest` can fail to encode and it is pushed directly.
+        let _ = self.queue.push(request, purpose);
+    }
+
+    fn handle_notification_source(&mut self, notification: Notification) {
+        match notification.event_id {
+            EventID::NotificationAdded | EventID::NotificationModified => match self.policy.rule_for(&notification) {
+                FetchRule::Skip => self.events.push_back(notification_event(notification, Vec::new())),
+                FetchRule::Fetch(attribute_ids) => self.scheduler.schedule(notification, attribute_ids),
+            },
+            EventID::NotificationRemoved => {
+                let uid = notification.notification_uid;
+                let scheduler = &mut self.scheduler;
+
+                scheduler.cancel(uid);
+
+                // Fetches already released give up their slot in the scheduler.
+                self.queue.retain(|_, purpose| {
+                    let cancelled = purpose.is_fetch_for(uid);
+                    if cancelled && matches!(purpose, Purpose::Fetch(_)) {
+                        scheduler.complete(uid);
+                    }
+                    !cancelled
+                });
+
+                if let Some(purpose) = self.queue.outstanding_tag_mut() {
+                    if purpose.is_fetch_for(uid) {
+                        if matches!(purpose, Purpose::Fetch(_)) {
+                            scheduler.complete(uid);
+                        }
+                        *purpose = Purpose::Cancelled;
+                    }
+                }
+
+                self.events.push_back(Event::NotificationRemoved(notification));
+            },
+            // Unknown events are only parsed in lenient mode and carry nothing to act on.
+            EventID::Unknown(_) => (),
+        }
+    }
+
+    fn handle_data_source(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        if let Some(completion) = self.queue.handle_data_source(bytes)? {
+            self.complete(completion);
+        }
+
+        Ok(())
+    }
+
+    fn complete(&mut self, completion: Completion<Purpose>) {
+        if let Purpose::Fetch(notification) = completion.tag() {
+            self.scheduler.complete(notification.notification_uid);
+        }
+
+        // The notification exists even though its attributes could not be
+        // fetched, so it is still reported with whatever did arrive.
+        let unfetched = match &completion {
+            Completion::Rejected { tag, .. } | Completion::TimedOut { tag, .. } => match tag {
+                Purpose::Fetch(notification) => Some(notification_event(*notification, Vec::new())),
+                Purpose::FollowUp(notification, attributes) => {
+                    Some(notification_event(*notification, attributes.clone()))
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let event = match completion {
+            Completion::Response { tag: Purpose::Cancelled, .. }
+            | Completion::Rejected { tag: Purpose::Cancelled, .. }
+            | Completion::TimedOut { tag: Purpose::Cancelled, .. } => return,
+            Completion::Response {
+                request: ControlPointRequest::GetNotificationAttributes(request),
+                tag: Purpose::Fetch(notification),
+                response: DataSourceResponse::GetNotificationAttributes(response),
+            } => {
+                let mut attributes = response.attribute_list;
+                let app_identifier = app_identifier(&attributes);
+                let follow_up = app_identifier
+                    .as_deref()
+                    .and_then(|app_identifier| self.policy.follow_up(app_identifier, &request.attribute_ids));
+
+                match follow_up {
+                    Some(attribute_ids) => {
+                        let request = GetNotificationAttributesRequest {
+                            notification_uid: notification.notification_uid,
+                            attribute_ids,
+                        };
+
+                        let purpose = Purpose::FollowUp(notification, attributes);
+                        self.enqueue(request.into(), purpose);
+                        return;
+                    },
+                    None => {
+                        if let Some(app_identifier) = app_identifier {
+                            self.policy.retain(&app_identifier, &mut attributes);
+                        }
+                        notification_event(notification, attributes)
+                    },
+                }
+            },
+            Completion::Response {
+                tag: Purpose::FollowUp(notification, mut attributes),
+                response: DataSourceResponse::GetNotificationAttributes(response),
+                ..
+            } => {
+                // A follow-up may fetch an attribute again with a different maximum length
+                attributes.retain(|attribute| {
+                    !response.attribute_list.iter().any(|fetched| fetched.id == attribute.id)
+                });
+                attributes.extend(response.attribute_list);
+                if let Some(app_identifier) = app_identifier(&attributes) {
+                    self.policy.retain(&app_identifier, &mut attributes);
+                }
+                notification_event(notification, attributes)
+            },
+            Completion::Response {
+                response: DataSourceResponse::GetNotificationAttributes(response),
+                ..
+            } => Event::NotificationAttributes(response),
+            Completion::Response {
+                tag: Purpose::AppName,
+                response: DataSourceResponse::GetAppAttributes(response),
+                ..
+            } => match self.app_names.handle_response(&response) {
+                Some(name) => Event::AppNameResolved {
+                    app_identifier: response.app_identifier,
+                    name: name.clone(),
+                },
+                None => {
+                    self.app_names.handle_failure(&response.app_identifier);
+                    return;
+                },
+            },
+            Completion::Response {
+                response: DataSourceResponse::GetAppAttributes(response),
+                ..
+            } => {
+                self.app_names.handle_response(&response);
+                Event::AppAttributes(response)
+            },
+            Completion::Accepted { request, .. } => Event::ActionPerformed(request),
+            Completion::Rejected { outcome, .. } => {
+                if let ControlPointRequest::GetAppAttributes(request) = &outcome.request {
+                    self.app_names.handle_failure(&request.app_identifier);
+                }
+                Event::CommandFailed(outcome)
+            },
+            Completion::TimedOut { request, .. } => {
+                if let ControlPointRequest::GetAppAttributes(request) = &request {
+                    self.app_names.handle_failure(&request.app_identifier);
+                }
+                Event::CommandTimedOut(request)
+            },
+        };
+
+        self.events.push_back(event);
+        self.events.extend(unfetched);
+    }
+}
+
+impl Purpose {
+    fn is_fetch_for(&self, notification_uid: u32) -> bool {
+        match self {
+            Purpose::Fetch(notification) | Purpose::FollowUp(notification, _) => {
+                notification.notification_uid == notification_uid
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Returns the fetched `AppIdentifier` among `attributes`, if any.
+fn app_identifier(attributes: &[NotificationAttribute]) -> Option<String> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.id == NotificationAttributeID::AppIdentifier)
+        .and_then(|attribute| attribute.value.clone())
+}
+
+/// Builds the `Event` reporting a fetched Notification Source event.
+fn notification_event(notification: Notification, attributes: Vec<NotificationAttribute>) -> Event {
+    match notification.event_id {
+        EventID::NotificationModified => Event::NotificationModified {
+            notification,
+            attributes,
+        },
+        _ => Event::NotificationAdded {
+            notification,
+            attributes,
+        },
+    }
+}
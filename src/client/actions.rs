@@ -0,0 +1,120 @@
+//! ## Actions
+//!
+//! A notification only accepts the actions its `EventFlag`s advertise with
+//! `PositiveAction` and `NegativeAction`; performing any other action makes iOS
+//! answer with Action Failed. The helpers here check the flags of a
+//! `TrackedNotification` before building a `PerformNotificationActionRequest`,
+//! and expose the `PositiveActionLabel` and `NegativeActionLabel` attributes as
+//! titles for buttons in a user interface.
+//!
+use crate::attributes::action::ActionID;
+use crate::attributes::event::EventFlag;
+use crate::attributes::notification::NotificationAttributeID;
+use crate::characteristics::control_point::{PerformNotificationActionRequest, RequestedAttribute};
+use crate::client::store::TrackedNotification;
+use crate::Error;
+
+/// The attributes holding the action labels, to add to a `FetchPolicy` so that
+/// `TrackedNotification::actions` can title them.
+pub const ACTION_LABELS: [RequestedAttribute; 2] = [
+    RequestedAttribute::PositiveActionLabel,
+    RequestedAttribute::NegativeActionLabel,
+];
+
+/// An action a notification offers, with its label if it was fetched.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Action<'a> {
+    pub action_id: ActionID,
+    pub label: Option<&'a str>,
+}
+
+impl TrackedNotification {
+    /// Determines if the notification offers the given action.
+    pub fn supports(&self, action_id: ActionID) -> bool {
+        match action_id {
+            ActionID::Positive => self.flags().contains(EventFlag::PositiveAction),
+            ActionID::Negative => self.flags().contains(EventFlag::NegativeAction),
+            ActionID::Unknown(_) => false,
+        }
+    }
+
+    /// Returns the label of the given action, if it was fetched.
+    pub fn action_label(&self, action_id: ActionID) -> Option<&str> {
+        match action_id {
+            ActionID::Positive => self.value(NotificationAttributeID::PositiveActionLabel),
+            ActionID::Negative => self.value(NotificationAttributeID::NegativeActionLabel),
+            ActionID::Unknown(_) => None,
+        }
+    }
+
+    /// Returns the actions the notification offers, positive first.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::NotificationAttribute;
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::actions::Action;
+    /// # use ancs::client::store::TrackedNotification;
+    /// let mut tracked = TrackedNotification::new(Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::NegativeAction,
+    ///     category_id: CategoryID::Social,
+    ///     category_count: 1,
+    ///     notification_uid: 8,
+    /// });
+    ///
+    /// tracked.merge_attributes(vec![NotificationAttribute {
+    ///     id: NotificationAttributeID::NegativeActionLabel,
+    ///     length: 5,
+    ///     value: Some("Clear".to_string()),
+    /// }]);
+    ///
+    /// assert_eq!(tracked.actions(), vec![Action { action_id: ActionID::Negative, label: Some("Clear") }]);
+    /// ```
+    pub fn actions(&self) -> Vec<Action<'_>> {
+        [ActionID::Positive, ActionID::Negative]
+            .into_iter()
+            .filter(|action_id| self.supports(*action_id))
+            .map(|action_id| Action {
+                action_id,
+                label: self.action_label(action_id),
+            })
+            .collect()
+    }
+
+    /// Builds the request performing the given action, if the notification offers it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::store::TrackedNotification;
+    /// let tracked = TrackedNotification::new(Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::PositiveAction,
+    ///     category_id: CategoryID::IncomingCall,
+    ///     category_count: 1,
+    ///     notification_uid: 8,
+    /// });
+    ///
+    /// assert_eq!(tracked.action_request(ActionID::Positive).map(|request| request.notification_uid), Ok(8));
+    /// assert_eq!(tracked.action_request(ActionID::Negative), Err(Error::ActionUnavailable(ActionID::Negative)));
+    /// ```
+    pub fn action_request(&self, action_id: ActionID) -> Result<PerformNotificationActionRequest, Error> {
+        if !self.supports(action_id) {
+            return Err(Error::ActionUnavailable(action_id));
+        }
+
+        Ok(PerformNotificationActionRequest {
+            notification_uid: self.uid(),
+            action_id,
+        })
+    }
+}
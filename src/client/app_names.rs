@@ -0,0 +1,220 @@
+//! ## App Name Cache
+//!
+//! Notifications only identify the app that posted them by its `AppIdentifier`,
+//! such as `com.apple.MobileSMS`. Its display name, such as `Messages`, has to be
+//! requested separately with a `GetAppAttributesRequest`. The `AppNameCache`
+//! makes sure each app is looked up only once, coalescing lookups made while a
+//! request is already in flight, and can export its contents so that names
+//! survive reconnecting to the Notification Provider.
+//!
+use std::collections::{HashMap, HashSet};
+
+use crate::attributes::app::AppAttributeID;
+use crate::characteristics::control_point::GetAppAttributesRequest;
+use crate::characteristics::data_source::GetAppAttributesResponse;
+
+/// The display name of an app, as reported by the Notification Provider.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AppName {
+    /// The app's display name.
+    Known(String),
+    /// The Notification Provider does not know the app, for example because it
+    /// was uninstalled, and answered with an empty display name.
+    Unknown,
+}
+
+impl AppName {
+    /// Returns the display name, if known.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AppName::Known(name) => Some(name),
+            AppName::Unknown => None,
+        }
+    }
+}
+
+/// The result of looking up an app in an `AppNameCache`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Lookup {
+    /// The app was already looked up.
+    Cached(AppName),
+    /// A request for the app is already in flight.
+    Pending,
+    /// The app has not been looked up yet and the given request should be sent.
+    Request(GetAppAttributesRequest),
+}
+
+/// The `AppNameCache` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::AppAttribute;
+/// # use ancs::attributes::app::AppAttributeID;
+/// # use ancs::attributes::command::CommandID;
+/// # use ancs::characteristics::data_source::GetAppAttributesResponse;
+/// # use ancs::client::app_names::{AppName, AppNameCache, Lookup};
+/// let mut cache = AppNameCache::new();
+///
+/// // Only the first lookup produces a request
+/// assert!(matches!(cache.lookup("com.apple.MobileSMS"), Lookup::Request(_)));
+/// assert_eq!(cache.lookup("com.apple.MobileSMS"), Lookup::Pending);
+///
+/// cache.handle_response(&GetAppAttributesResponse {
+///     command_id: CommandID::GetAppAttributes,
+///     app_identifier: "com.apple.MobileSMS".to_string(),
+///     attribute_list: vec![AppAttribute {
+///         id: AppAttributeID::DisplayName,
+///         length: 8,
+///         value: Some("Messages".to_string()),
+///     }],
+/// });
+///
+/// assert_eq!(cache.lookup("com.apple.MobileSMS"), Lookup::Cached(AppName::Known("Messages".to_string())));
+///
+/// // The names can be carried over to a new connection
+/// let restored = AppNameCache::new().with_entries(cache.export());
+/// assert_eq!(restored.display_name("com.apple.MobileSMS"), Some("Messages"));
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AppNameCache {
+    names: HashMap<String, AppName>,
+    pending: HashSet<String>,
+}
+
+impl AppNameCache {
+    /// Creates an empty `AppNameCache`.
+    pub fn new() -> AppNameCache {
+        AppNameCache::default()
+    }
+
+    /// Adds previously exported entries to the cache.
+    pub fn with_entries<I>(mut self, entries: I) -> AppNameCache
+    where
+        I: IntoIterator<Item = (String, AppName)>,
+    {
+        self.import(entries);
+        self
+    }
+
+    /// Looks up an app, returning a request to send if it has not been looked
+    /// up yet. The app is considered pending until `handle_response` or
+    /// `handle_failure` is called for it.
+    pub fn lookup(&mut self, app_identifier: &str) -> Lookup {
+        if let Some(name) = self.names.get(app_identifier) {
+            return Lookup::Cached(name.clone());
+        }
+
+        if !self.pending.insert(app_identifier.to_string()) {
+            return Lookup::Pending;
+        }
+
+        Lookup::Request(GetAppAttributesRequest {
+            app_identifier: app_identifier.to_string(),
+            attribute_ids: vec![AppAttributeID::DisplayName],
+        })
+    }
+
+    /// Fills the cache from a `GetAppAttributesResponse`. Responses without a
+    /// display name attribute are ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::AppAttribute;
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// # use ancs::attributes::command::CommandID;
+    /// # use ancs::characteristics::data_source::GetAppAttributesResponse;
+    /// # use ancs::client::app_names::{AppName, AppNameCache};
+    /// let mut cache = AppNameCache::new();
+    ///
+    /// let name = cache.handle_response(&GetAppAttributesResponse {
+    ///     command_id: CommandID::GetAppAttributes,
+    ///     app_identifier: "com.example.removed".to_string(),
+    ///     attribute_list: vec![AppAttribute {
+    ///         id: AppAttributeID::DisplayName,
+    ///         length: 0,
+    ///         value: None,
+    ///     }],
+    /// });
+    ///
+    /// assert_eq!(name, Some(&AppName::Unknown));
+    /// assert_eq!(cache.display_name("com.example.removed"), None);
+    /// assert_eq!(cache.contains("com.example.removed"), true);
+    /// ```
+    pub fn handle_response(&mut self, response: &GetAppAttributesResponse) -> Option<&AppName> {
+        let attribute = response
+            .attribute_list
+            .iter()
+            .find(|attribute| attribute.id == AppAttributeID::DisplayName)?;
+
+        let name = match attribute.value.as_deref() {
+            Some(name) if !name.is_empty() => AppName::Known(name.to_string()),
+            _ => AppName::Unknown,
+        };
+
+        self.pending.remove(&response.app_identifier);
+        self.names.insert(response.app_identifier.clone(), name);
+        self.names.get(&response.app_identifier)
+    }
+
+    /// Marks a lookup as failed, for example because its request was rejected
+    /// or timed out, so that the next `lookup` requests it again.
+    pub fn handle_failure(&mut self, app_identifier: &str) {
+        self.pending.remove(app_identifier);
+    }
+
+    /// Forgets every lookup in flight, for example after the Notification
+    /// Provider disconnects. Cached names are kept.
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Returns the cached name of an app, if it was looked up.
+    pub fn get(&self, app_identifier: &str) -> Option<&AppName> {
+        self.names.get(app_identifier)
+    }
+
+    /// Returns the display name of an app, if it was looked up and is known.
+    pub fn display_name(&self, app_identifier: &str) -> Option<&str> {
+        self.get(app_identifier).and_then(AppName::as_str)
+    }
+
+    /// Determines if an app was looked up.
+    pub fn contains(&self, app_identifier: &str) -> bool {
+        self.names.contains_key(app_identifier)
+    }
+
+    /// Determines if a request for an app is in flight.
+    pub fn is_pending(&self, app_identifier: &str) -> bool {
+        self.pending.contains(app_identifier)
+    }
+
+    /// Returns every cached name. Lookups in flight are not included.
+    pub fn export(&self) -> Vec<(String, AppName)> {
+        self.names
+            .iter()
+            .map(|(app_identifier, name)| (app_identifier.clone(), name.clone()))
+            .collect()
+    }
+
+    /// Adds previously exported entries, replacing any cached name for the same app.
+    pub fn import<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (String, AppName)>,
+    {
+        for (app_identifier, name) in entries {
+            self.pending.remove(&app_identifier);
+            self.names.insert(app_identifier, name);
+        }
+    }
+
+    /// Returns the number of cached names.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Determines if no name is cached.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
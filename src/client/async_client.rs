@@ -0,0 +1,459 @@
+//! ## Async Client
+//!
+//! Available with the `async` feature. The `AsyncClient` drives an `AncsClient`
+//! over an `AsyncTransport`, so that applications built on an async runtime such
+//! as tokio can consume ANCS as a `Stream` of `Event`s and simply `.await` the
+//! responses to their own requests. It does not depend on any runtime: the
+//! transport supplies the I/O and, optionally, the timer used for timeouts.
+//!
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::pin;
+use std::time::Instant;
+
+use futures_util::future::{select, Either};
+use futures_util::Stream;
+use uuid::Uuid;
+
+use crate::attributes::action::ActionID;
+use crate::attributes::app::AppAttributeID;
+use crate::characteristics::control_point::*;
+use crate::characteristics::data_source::*;
+use crate::client::clock::{Clock, SystemClock};
+use crate::client::{AncsClient, Event, SUBSCRIPTIONS};
+use crate::Error;
+
+/// The GATT operations an `AsyncClient` needs from a Bluetooth stack.
+pub trait AsyncTransport {
+    /// The error reported by the Bluetooth stack.
+    type Error;
+
+    /// Enables notifications on one of the ANCS characteristics.
+    fn enable_notifications(&mut self, characteristic: Uuid) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Writes to the Control Point with response, reporting a `ControlPointError`
+    /// if the Notification Provider rejects the write.
+    fn write_control_point(&mut self, bytes: &[u8]) -> impl Future<Output = Result<(), WriteError<Self::Error>>> + Send;
+
+    /// Waits for the next notification on the Notification Source or Data Source.
+    ///
+    /// While a command is outstanding, this future is raced against
+    /// `sleep_until` and dropped if the deadline comes first, so it must be
+    /// cancel-safe: a notification must not be lost when the future is dropped
+    /// before completing. Receiving from a channel fed by the Bluetooth stack,
+    /// as most stacks offer, is.
+    fn receive(&mut self) -> impl Future<Output = Result<(Uuid, Vec<u8>), Self::Error>> + Send;
+
+    /// Waits until `deadline` on the wall clock, so that commands the
+    /// Notification Provider never answers can time out. By default this never
+    /// completes, in which case timeouts are only noticed when a notification
+    /// arrives.
+    ///
+    /// The client keeps its deadlines on its own `Clock`, so the `AsyncClient`
+    /// converts them to the wall clock first. With a `ManualClock` that has not
+    /// reached the deadline by the time this completes, the client goes on
+    /// waiting for a notification alone, as nothing can time out until the
+    /// clock is advanced.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::future::Future;
+    /// # use std::task::Poll;
+    /// # use std::time::Instant;
+    /// # use uuid::Uuid;
+    /// # use ancs::characteristics::control_point::{RequestedAttribute, WriteError};
+    /// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+    /// # use ancs::client::AncsClient;
+    /// # use ancs::client::async_client::{AsyncClient, AsyncTransport};
+    /// # use ancs::client::clock::ManualClock;
+    /// /// A transport whose timer fires at once and whose notifications take a moment.
+    /// struct Hasty(Vec<u8>);
+    ///
+    /// impl AsyncTransport for Hasty {
+    ///     type Error = ();
+    ///
+    ///     async fn enable_notifications(&mut self, _: Uuid) -> Result<(), ()> {
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn write_control_point(&mut self, _: &[u8]) -> Result<(), WriteError<()>> {
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn receive(&mut self) -> Result<(Uuid, Vec<u8>), ()> {
+    ///         let mut yielded = false;
+    ///         std::future::poll_fn(|cx| {
+    ///             if yielded {
+    ///                 return Poll::Ready(());
+    ///             }
+    ///             yielded = true;
+    ///             cx.waker().wake_by_ref();
+    ///             Poll::Pending
+    ///         })
+    ///         .await;
+    ///         Ok((DATA_SOURCE_UUID, self.0.clone()))
+    ///     }
+    ///
+    ///     fn sleep_until(_: Instant) -> impl Future<Output = ()> + Send {
+    ///         std::future::ready(())
+    ///     }
+    /// }
+    ///
+    /// // The manual clock never reaches the deadline, so the response is awaited
+    /// let transport = Hasty(vec![0, 7, 0, 0, 0, 3, 1, 0, 53]);
+    /// let mut client = AsyncClient::with_client(transport, AncsClient::with_clock(ManualClock::new()));
+    ///
+    /// let response = futures_executor::block_on(
+    ///     client.get_notification_attributes(7, vec![RequestedAttribute::MessageSize]),
+    /// );
+    /// assert_eq!(response.unwrap().attribute_list[0].value, Some("5".to_string()));
+    /// ```
+    fn sleep_until(deadline: Instant) -> impl Future<Output = ()> + Send {
+        let _ = deadline;
+        std::future::pending()
+    }
+}
+
+/// Why an `AsyncClient` operation failed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum AsyncError<E> {
+    /// The transport failed.
+    Transport(E),
+    /// Bytes received from the Notification Provider could not be handled.
+    Protocol(Error),
+    /// The Notification Provider rejected the request.
+    Rejected(ControlPointError),
+    /// The Notification Provider did not answer the request in time.
+    TimedOut,
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for AsyncError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsyncError::Transport(error) => write!(f, "transport error: {}", error),
+            AsyncError::Protocol(error) => write!(f, "protocol error: {}", error),
+            AsyncError::Rejected(error) => write!(f, "request rejected: {}", error),
+            AsyncError::TimedOut => write!(f, "request timed out"),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for AsyncError<E> {}
+
+/// The `AsyncClient` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use std::collections::VecDeque;
+/// # use std::pin::pin;
+/// # use futures_util::StreamExt;
+/// # use uuid::Uuid;
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::characteristics::control_point::{RequestedAttribute, WriteError};
+/// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+/// # use ancs::characteristics::notification_source::{Notification, NOTIFICATION_SOURCE_UUID};
+/// # use ancs::client::{AncsClient, Event};
+/// # use ancs::client::async_client::{AsyncClient, AsyncTransport};
+/// /// A transport replaying canned notifications.
+/// struct Replay(VecDeque<(Uuid, Vec<u8>)>);
+///
+/// impl AsyncTransport for Replay {
+///     type Error = ();
+///
+///     async fn enable_notifications(&mut self, _: Uuid) -> Result<(), ()> {
+///         Ok(())
+///     }
+///
+///     async fn write_control_point(&mut self, _: &[u8]) -> Result<(), WriteError<()>> {
+///         Ok(())
+///     }
+///
+///     async fn receive(&mut self) -> Result<(Uuid, Vec<u8>), ()> {
+///         self.0.pop_front().ok_or(())
+///     }
+/// }
+///
+/// let added: [u8; 8] = Notification {
+///     event_id: EventID::NotificationAdded,
+///     event_flags: EventFlag::empty(),
+///     category_id: CategoryID::Social,
+///     category_count: 1,
+///     notification_uid: 7,
+/// }.into();
+/// let transport = Replay(VecDeque::from([
+///     (NOTIFICATION_SOURCE_UUID, added.to_vec()),
+///     (DATA_SOURCE_UUID, vec![0, 7, 0, 0, 0, 1, 2, 0, 72, 105]),
+///     (DATA_SOURCE_UUID, vec![0, 7, 0, 0, 0, 3, 1, 0, 53]),
+/// ]));
+///
+/// let client = AncsClient::new().with_attributes(vec![RequestedAttribute::Title(16)]);
+/// let mut client = AsyncClient::with_client(transport, client);
+///
+/// futures_executor::block_on(async {
+///     client.subscribe().await.unwrap();
+///
+///     let event = pin!(client.events()).next().await.unwrap().unwrap();
+///     assert!(matches!(event, Event::NotificationAdded { .. }));
+///
+///     let response = client
+///         .get_notification_attributes(7, vec![RequestedAttribute::MessageSize])
+///         .await
+///         .unwrap();
+///     assert_eq!(response.attribute_list[0].value, Some("5".to_string()));
+/// });
+/// ```
+#[derive(Debug)]
+pub struct AsyncClient<T, C = SystemClock> {
+    client: AncsClient<C>,
+    transport: T,
+    /// Events received while waiting for the response to a request.
+    events: VecDeque<Event>,
+}
+
+impl<T: AsyncTransport> AsyncClient<T> {
+    /// Creates an `AsyncClient` driving a default `AncsClient` over `transport`.
+    pub fn new(transport: T) -> AsyncClient<T> {
+        AsyncClient::with_client(transport, AncsClient::new())
+    }
+}
+
+impl<T: AsyncTransport, C: Clock> AsyncClient<T, C> {
+    /// Creates an `AsyncClient` driving the given `AncsClient` over `transport`.
+    pub fn with_client(transport: T, client: AncsClient<C>) -> AsyncClient<T, C> {
+        AsyncClient {
+            client,
+            transport,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Returns the `AncsClient` being driven.
+    pub fn client(&self) -> &AncsClient<C> {
+        &self.client
+    }
+
+    /// Returns the `AncsClient` being driven, for example to queue a request
+    /// whose outcome is reported as an `Event`.
+    pub fn client_mut(&mut self) -> &mut AncsClient<C> {
+        &mut self.client
+    }
+
+    /// Returns the transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Consumes the `AsyncClient`, returning the `AncsClient` and the transport.
+    pub fn into_parts(self) -> (AncsClient<C>, T) {
+        (self.client, self.transport)
+    }
+
+    /// Enables notifications on the characteristics in `SUBSCRIPTIONS`, in order.
+    pub async fn subscribe(&mut self) -> Result<(), AsyncError<T::Error>> {
+        for characteristic in SUBSCRIPTIONS {
+            self.transport
+                .enable_notifications(characteristic)
+                .await
+                .map_err(AsyncError::Transport)?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the next `Event`.
+    ///
+    /// Bytes from the Notification Provider that cannot be handled are reported
+    /// as `AsyncError::Protocol`, after which the client can keep being used.
+    pub async fn next_event(&mut self) -> Result<Event, AsyncError<T::Error>> {
+        loop {
+            if let Some(event) = self.events.pop_front().or_else(|| self.client.poll_event()) {
+                return Ok(event);
+            }
+
+            self.drive().await?;
+        }
+    }
+
+    /// Returns a `Stream` of every `Event`. A protocol error is yielded as an
+    /// item and the stream goes on, while a transport error is yielded as the
+    /// last item, since a failed transport cannot produce any further events.
+    ///
+    /// # Examples
+    /// ```
+    /// # use futures_util::StreamExt;
+    /// # use uuid::Uuid;
+    /// # use ancs::characteristics::control_point::WriteError;
+    /// # use ancs::client::async_client::{AsyncClient, AsyncError, AsyncTransport};
+    /// /// A transport whose link is already lost.
+    /// struct Disconnected;
+    ///
+    /// impl AsyncTransport for Disconnected {
+    ///     type Error = &'static str;
+    ///
+    ///     async fn enable_notifications(&mut self, _: Uuid) -> Result<(), Self::Error> {
+    ///         Err("disconnected")
+    ///     }
+    ///
+    ///     async fn write_control_point(&mut self, _: &[u8]) -> Result<(), WriteError<Self::Error>> {
+    ///         Err(WriteError::Transport("disconnected"))
+    ///     }
+    ///
+    ///     async fn receive(&mut self) -> Result<(Uuid, Vec<u8>), Self::Error> {
+    ///         Err("disconnected")
+    ///     }
+    /// }
+    ///
+    /// let mut client = AsyncClient::new(Disconnected);
+    /// let events: Vec<_> = futures_executor::block_on(client.events().collect());
+    ///
+    /// assert_eq!(events, vec![Err(AsyncError::Transport("disconnected"))]);
+    /// ```
+    pub fn events(&mut self) -> impl Stream<Item = Result<Event, AsyncError<T::Error>>> + '_ {
+        futures_util::stream::unfold(Some(self), |client| async move {
+            let client = client?;
+            match client.next_event().await {
+                Err(AsyncError::Transport(error)) => Some((Err(AsyncError::Transport(error)), None)),
+                event => Some((event, Some(client))),
+            }
+        })
+    }
+
+    /// Requests the attributes of a notification and waits for the response.
+    pub async fn get_notification_attributes(
+        &mut self,
+        notification_uid: u32,
+        attribute_ids: Vec<RequestedAttribute>,
+    ) -> Result<GetNotificationAttributesResponse, AsyncError<T::Error>> {
+        let request = GetNotificationAttributesRequest {
+            notification_uid,
+            attribute_ids,
+        };
+
+        self.client
+            .get_notification_attributes(request.notification_uid, request.attribute_ids.clone());
+
+        self.wait_for(request.into(), |event| match event {
+            Event::NotificationAttributes(response) if response.notification_uid == notification_uid => Ok(response),
+            event => Err(event),
+        })
+        .await
+    }
+
+    /// Requests the attributes of an app and waits for the response.
+    pub async fn get_app_attributes(
+        &mut self,
+        app_identifier: String,
+        attribute_ids: Vec<AppAttributeID>,
+    ) -> Result<GetAppAttributesResponse, AsyncError<T::Error>> {
+        let request = GetAppAttributesRequest {
+            app_identifier,
+            attribute_ids,
+        };
+
+        self.client
+            .get_app_attributes(request.app_identifier.clone(), request.attribute_ids.clone())
+            .map_err(AsyncError::Protocol)?;
+
+        let app_identifier = request.app_identifier.clone();
+        self.wait_for(request.into(), |event| match event {
+            Event::AppAttributes(response) if response.app_identifier == app_identifier => Ok(response),
+            event => Err(event),
+        })
+        .await
+    }
+
+    /// Performs an action on a notification and waits until the Notification
+    /// Provider accepts it.
+    pub async fn perform_notification_action(
+        &mut self,
+        notification_uid: u32,
+        action_id: ActionID,
+    ) -> Result<(), AsyncError<T::Error>> {
+        let request = PerformNotificationActionRequest {
+            notification_uid,
+            action_id,
+        };
+
+        self.client.perform_notification_action(notification_uid, action_id);
+
+        let expected = request.clone();
+        self.wait_for(request.into(), |event| match event {
+            Event::ActionPerformed(performed) if performed == expected => Ok(()),
+            event => Err(event),
+        })
+        .await
+    }
+
+    /// Drives the client until `matches` accepts an `Event`, or `request` fails.
+    /// Every other event is kept for `next_event`.
+    async fn wait_for<R, F>(&mut self, request: ControlPointRequest, mut matches: F) -> Result<R, AsyncError<T::Error>>
+    where
+        F: FnMut(Event) -> Result<R, Event>,
+    {
+        loop {
+            while let Some(event) = self.client.poll_event() {
+                let event = match event {
+                    Event::CommandFailed(outcome) if outcome.request == request => {
+                        if let Some(error) = outcome.error() {
+                            return Err(AsyncError::Rejected(error));
+                        }
+                        Event::CommandFailed(outcome)
+                    },
+                    Event::CommandTimedOut(failed) if failed == request => return Err(AsyncError::TimedOut),
+                    event => event,
+                };
+
+                match matches(event) {
+                    Ok(response) => return Ok(response),
+                    Err(event) => self.events.push_back(event),
+                }
+            }
+
+            match self.drive().await {
+                // Stray bytes must not fail an unrelated request.
+                Err(AsyncError::Protocol(_)) => continue,
+                result => result?,
+            }
+        }
+    }
+
+    /// Performs a single step of I/O: a pending Control Point write, otherwise
+    /// waiting for a notification or for the outstanding command to time out.
+    async fn drive(&mut self) -> Result<(), AsyncError<T::Error>> {
+        if let Some(bytes) = self.client.poll_write() {
+            let result = self.transport.write_control_point(&bytes).await;
+            return self.client.handle_write(result).map_err(AsyncError::Transport);
+        }
+
+        let received = match self.client.poll_timeout() {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(self.client.clock().now());
+                let receive = pin!(self.transport.receive());
+                let sleep = pin!(T::sleep_until(Instant::now() + remaining));
+
+                match select(receive, sleep).await {
+                    Either::Left((received, _)) => Some(received),
+                    // A clock other than the wall clock may not have moved while
+                    // sleeping, and looping again would only busy wait on it.
+                    Either::Right((_, receive)) if self.client.clock().now() < deadline => Some(receive.await),
+                    Either::Right(_) => None,
+                }
+            },
+            None => Some(self.transport.receive().await),
+        };
+
+        match received {
+            Some(received) => {
+                let (characteristic, bytes) = received.map_err(AsyncError::Transport)?;
+                self.client
+                    .handle_notification(characteristic, &bytes)
+                    .map_err(AsyncError::Protocol)
+            },
+            None => {
+                self.client.handle_timeout();
+                Ok(())
+            },
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! ## Calls
+//!
+//! ANCS reports phone calls as ordinary notifications: a ringing call is an
+//! `IncomingCall` notification that is removed once the call stops ringing, and
+//! a call that was not picked up comes back as a separate `MissedCall`
+//! notification, possibly followed by a `Voicemail` one. The `CallTracker`
+//! stitches these notifications back together into `Call`s, takes the caller's
+//! name from the `Title` attribute, and maps answering and declining the ringing
+//! call to `ActionID::Positive` and `ActionID::Negative`.
+//!
+use std::collections::VecDeque;
+
+use crate::attributes::action::ActionID;
+use crate::attributes::category::CategoryID;
+use crate::attributes::event::EventFlag;
+use crate::attributes::notification::NotificationAttributeID;
+use crate::attributes::NotificationAttribute;
+use crate::characteristics::control_point::{ControlPointRequest, PerformNotificationActionRequest};
+use crate::characteristics::notification_source::Notification;
+use crate::client::Event;
+use crate::Error;
+
+/// The number of calls a `CallTracker` remembers by default.
+pub const DEFAULT_HISTORY: usize = 16;
+
+/// The state of a `Call`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum CallState {
+    /// The call is ringing.
+    Ringing,
+    /// The call was answered from the Notification Consumer.
+    Answered,
+    /// The call was declined from the Notification Consumer.
+    Declined,
+    /// The call stopped ringing without being answered or declined here. It was
+    /// either picked up on the phone or is about to be reported as missed.
+    Ended,
+    /// The call was missed.
+    Missed,
+    /// The caller left a voicemail.
+    VoicemailReceived,
+}
+
+/// A phone call assembled from its ANCS notifications.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Call {
+    /// The UID of the notification that first reported the call.
+    pub notification_uid: u32,
+    /// The UID of the latest `MissedCall` or `Voicemail` notification for the call.
+    pub follow_up_uid: Option<u32>,
+    /// The caller, as given by the `Title` attribute, if it was fetched.
+    pub caller: Option<String>,
+    pub state: CallState,
+    /// The `EventFlag`s of the incoming call notification.
+    pub flags: EventFlag,
+    /// The action requested from the Notification Provider and not yet confirmed.
+    pub pending_action: Option<ActionID>,
+}
+
+impl Call {
+    fn new(notification: &Notification, caller: Option<String>, state: CallState) -> Call {
+        Call {
+            notification_uid: notification.notification_uid,
+            follow_up_uid: None,
+            caller,
+            state,
+            flags: notification.event_flags,
+            pending_action: None,
+        }
+    }
+
+    fn involves(&self, notification_uid: u32) -> bool {
+        self.notification_uid == notification_uid || self.follow_up_uid == Some(notification_uid)
+    }
+}
+
+/// The `CallTracker` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::NotificationAttribute;
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::attributes::notification::NotificationAttributeID;
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::client::calls::{CallState, CallTracker};
+/// let mut tracker = CallTracker::new();
+/// let title = vec![NotificationAttribute {
+///     id: NotificationAttributeID::Title,
+///     length: 3,
+///     value: Some("Ann".to_string()),
+/// }];
+/// let mut notification = Notification {
+///     event_id: EventID::NotificationAdded,
+///     event_flags: EventFlag::PositiveAction | EventFlag::NegativeAction,
+///     category_id: CategoryID::IncomingCall,
+///     category_count: 1,
+///     notification_uid: 10,
+/// };
+///
+/// tracker.handle_added(&notification, &title);
+/// assert_eq!(tracker.ringing().map(|call| call.caller.as_deref()), Some(Some("Ann")));
+///
+/// // The call stops ringing and comes back as a missed call
+/// notification.event_id = EventID::NotificationRemoved;
+/// tracker.handle_removed(&notification);
+///
+/// notification.event_id = EventID::NotificationAdded;
+/// notification.category_id = CategoryID::MissedCall;
+/// notification.notification_uid = 11;
+/// tracker.handle_added(&notification, &title);
+///
+/// let call = tracker.get(10).unwrap();
+/// assert_eq!(call.state, CallState::Missed);
+/// assert_eq!(call.follow_up_uid, Some(11));
+/// assert_eq!(tracker.calls().count(), 1);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct CallTracker {
+    history: usize,
+    /// Calls from oldest to newest.
+    calls: VecDeque<Call>,
+}
+
+impl Default for CallTracker {
+    fn default() -> Self {
+        CallTracker::new()
+    }
+}
+
+impl CallTracker {
+    /// Creates a `CallTracker` remembering up to `DEFAULT_HISTORY` calls.
+    pub fn new() -> CallTracker {
+        CallTracker {
+            history: DEFAULT_HISTORY,
+            calls: VecDeque::new(),
+        }
+    }
+
+    /// Sets how many calls are remembered before the oldest is forgotten.
+    pub fn with_history(mut self, history: usize) -> CallTracker {
+        self.history = history.max(1);
+        self.truncate();
+        self
+    }
+
+    /// Applies an `Event` produced by an `AncsClient`, ignoring events that do
+    /// not concern calls.
+    pub fn apply_event(&mut self, event: &Event) {
+        match event {
+            Event::NotificationAdded { notification, attributes }
+            | Event::NotificationModified { notification, attributes } => {
+                self.handle_added(notification, attributes);
+            },
+            Event::NotificationRemoved(notification) => self.handle_removed(notification),
+            Event::ActionPerformed(request) => self.handle_action_result(request, true),
+            Event::CommandFailed(outcome) => {
+                if let ControlPointRequest::PerformNotificationAction(request) = &outcome.request {
+                    self.handle_action_result(request, false);
+                }
+            },
+            Event::CommandTimedOut(ControlPointRequest::PerformNotificationAction(request)) => {
+                self.handle_action_result(request, false);
+            },
+            _ => (),
+        }
+    }
+
+    /// Handles an added or modified notification and its fetched attributes.
+    pub fn handle_added(&mut self, notification: &Notification, attributes: &[NotificationAttribute]) {
+        let uid = notification.notification_uid;
+        let caller = attributes
+            .iter()
+            .find(|attribute| attribute.id == NotificationAttributeID::Title)
+            .and_then(|attribute| attribute.value.clone());
+
+        if let Some(call) = self.calls.iter_mut().find(|call| call.involves(uid)) {
+            if call.notification_uid == uid {
+                call.flags = notification.event_flags;
+            }
+            if caller.is_some() {
+                call.caller = caller;
+            }
+            return;
+        }
+
+        match notification.category_id {
+            CategoryID::IncomingCall => self.push(Call::new(notification, caller, CallState::Ringing)),
+            CategoryID::MissedCall => {
+                let call = self.find_for_caller(&caller, &[CallState::Ringing, CallState::Ended]);
+                self.follow_up(call, notification, caller, CallState::Missed);
+            },
+            CategoryID::Voicemail => {
+                let states = [CallState::Ended, CallState::Missed, CallState::Declined];
+                let call = self.find_for_caller(&caller, &states);
+                self.follow_up(call, notification, caller, CallState::VoicemailReceived);
+            },
+            _ => (),
+        }
+    }
+
+    /// Handles a removed notification. A ringing call whose notification is
+    /// removed stopped ringing.
+    ///
+    /// Answering or declining a call removes its notification, usually before the
+    /// action's result arrives, so a pending action is kept until
+    /// `handle_action_result` settles it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::calls::{CallState, CallTracker};
+    /// let mut tracker = CallTracker::new();
+    /// let mut notification = Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::PositiveAction | EventFlag::NegativeAction,
+    ///     category_id: CategoryID::IncomingCall,
+    ///     category_count: 1,
+    ///     notification_uid: 4,
+    /// };
+    /// tracker.handle_added(&notification, &[]);
+    /// let request = tracker.answer().unwrap();
+    ///
+    /// // The call stops ringing before the Control Point write is acknowledged
+    /// notification.event_id = EventID::NotificationRemoved;
+    /// tracker.handle_removed(&notification);
+    /// assert_eq!(tracker.get(4).map(|call| call.state), Some(CallState::Ended));
+    ///
+    /// tracker.handle_action_result(&request, true);
+    /// assert_eq!(tracker.get(4).map(|call| call.state), Some(CallState::Answered));
+    /// ```
+    pub fn handle_removed(&mut self, notification: &Notification) {
+        let uid = notification.notification_uid;
+
+        if let Some(call) = self.calls.iter_mut().find(|call| call.notification_uid == uid) {
+            if call.state == CallState::Ringing {
+                call.state = CallState::Ended;
+            }
+        }
+    }
+
+    /// Handles the outcome of an action requested with `answer` or `decline`.
+    pub fn handle_action_result(&mut self, request: &PerformNotificationActionRequest, accepted: bool) {
+        let call = match self
+            .calls
+            .iter_mut()
+            .find(|call| call.notification_uid == request.notification_uid)
+        {
+            Some(call) => call,
+            None => return,
+        };
+
+        if call.pending_action != Some(request.action_id) {
+            return;
+        }
+
+        call.pending_action = None;
+
+        if !accepted || !matches!(call.state, CallState::Ringing | CallState::Ended) {
+            return;
+        }
+
+        call.state = match request.action_id {
+            ActionID::Positive => CallState::Answered,
+            _ => CallState::Declined,
+        };
+    }
+
+    /// Builds the request answering the ringing call. The call is `Answered`
+    /// once the Notification Provider accepts the action.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::client::calls::CallTracker;
+    /// let mut tracker = CallTracker::new();
+    ///
+    /// assert_eq!(tracker.answer(), Err(Error::NoRingingCall));
+    /// ```
+    pub fn answer(&mut self) -> Result<PerformNotificationActionRequest, Error> {
+        self.request_action(ActionID::Positive)
+    }
+
+    /// Builds the request declining the ringing call. The call is `Declined`
+    /// once the Notification Provider accepts the action.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::Event;
+    /// # use ancs::client::calls::{CallState, CallTracker};
+    /// let mut tracker = CallTracker::new();
+    /// tracker.handle_added(&Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::PositiveAction | EventFlag::NegativeAction,
+    ///     category_id: CategoryID::IncomingCall,
+    ///     category_count: 1,
+    ///     notification_uid: 3,
+    /// }, &[]);
+    ///
+    /// let request = tracker.decline().unwrap();
+    /// assert_eq!((request.notification_uid, request.action_id), (3, ActionID::Negative));
+    ///
+    /// tracker.apply_event(&Event::ActionPerformed(request));
+    /// assert_eq!(tracker.get(3).map(|call| call.state), Some(CallState::Declined));
+    /// ```
+    pub fn decline(&mut self) -> Result<PerformNotificationActionRequest, Error> {
+        self.request_action(ActionID::Negative)
+    }
+
+    /// Returns the most recent ringing call, if any.
+    pub fn ringing(&self) -> Option<&Call> {
+        self.calls.iter().rev().find(|call| call.state == CallState::Ringing)
+    }
+
+    /// Returns the call a notification belongs to, if any.
+    pub fn get(&self, notification_uid: u32) -> Option<&Call> {
+        self.calls.iter().find(|call| call.involves(notification_uid))
+    }
+
+    /// Returns the remembered calls from oldest to newest.
+    pub fn calls(&self) -> impl Iterator<Item = &Call> {
+        self.calls.iter()
+    }
+
+    /// Forgets every call.
+    pub fn clear(&mut self) {
+        self.calls.clear();
+    }
+
+    fn request_action(&mut self, action_id: ActionID) -> Result<PerformNotificationActionRequest, Error> {
+        let flag = match action_id {
+            ActionID::Positive => EventFlag::PositiveAction,
+            _ => EventFlag::NegativeAction,
+        };
+
+        let call = self
+            .calls
+            .iter_mut()
+            .rev()
+            .find(|call| call.state == CallState::Ringing)
+            .ok_or(Error::NoRingingCall)?;
+
+        if !call.flags.contains(flag) {
+            return Err(Error::ActionUnavailable(action_id));
+        }
+
+        call.pending_action = Some(action_id);
+
+        Ok(PerformNotificationActionRequest {
+            notification_uid: call.notification_uid,
+            action_id,
+        })
+    }
+
+    fn find_for_caller(&self, caller: &Option<String>, states: &[CallState]) -> Option<usize> {
+        self.calls
+            .iter()
+            .rposition(|call| states.contains(&call.state) && &call.caller == caller)
+    }
+
+    fn follow_up(&mut self, index: Option<usize>, notification: &Notification, caller: Option<String>, state: CallState) {
+        match index.and_then(|index| self.calls.get_mut(index)) {
+            Some(call) => {
+                call.follow_up_uid = Some(notification.notification_uid);
+                call.state = state;
+            },
+            None => {
+                let mut call = Call::new(notification, caller, state);
+                call.flags = EventFlag::empty();
+                self.push(call);
+            },
+        }
+    }
+
+    fn push(&mut self, call: Call) {
+        self.calls.push_back(call);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.calls.len() > self.history {
+            self.calls.pop_front();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! ## Clock
+//!
+//! Time is injected into the client through the `Clock` trait so that timeouts
+//! can be tested deterministically. `SystemClock` reads the monotonic system clock
+//! while `ManualClock` only moves when it is told to.
+//!
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of monotonic time.
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+/// A `Clock` backed by `Instant::now`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A `Clock` that only advances when told to. Clones share the same time, so a
+/// test can keep one handle while the code under test owns another.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use ancs::client::clock::{Clock, ManualClock};
+/// let clock = ManualClock::new();
+/// let handle = clock.clone();
+/// let start = clock.now();
+///
+/// handle.advance(Duration::from_secs(5));
+///
+/// assert_eq!(clock.now() - start, Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl ManualClock {
+    /// Creates a `ManualClock` starting at the current time.
+    pub fn new() -> ManualClock {
+        ManualClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *now += duration;
+    }
+
+    /// Moves the clock forward to `instant`, if it lies in the future.
+    pub fn advance_to(&self, instant: Instant) {
+        let mut now = self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *now = (*now).max(instant);
+    }
+
+    /// Determines if `other` is a clone of this clock, so that advancing one
+    /// advances the other.
+    pub fn is_shared_with(&self, other: &ManualClock) -> bool {
+        Arc::ptr_eq(&self.now, &other.now)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,224 @@
+//! ## Fetch Policy
+//!
+//! Nearly every added or modified notification is followed by the same
+//! `GetNotificationAttributesRequest`. A `FetchPolicy` declares which attributes
+//! to request, and with which maximum lengths, per `CategoryID`, with optional
+//! overrides per app. It can also skip fetching altogether, for example for
+//! `Silent` notifications.
+//!
+//! A notification only reveals its app once its `AppIdentifier` has been fetched,
+//! so while per-app overrides are configured the first fetch always includes it
+//! and any further attributes an override asks for are fetched in a follow-up.
+//! Attributes the first fetch returned that an override does not ask for are
+//! dropped before the notification is reported, so an app whose rule is
+//! `FetchRule::Skip` is reported with its `AppIdentifier` only.
+//!
+use std::collections::HashMap;
+
+use crate::attributes::category::CategoryID;
+use crate::attributes::event::EventFlag;
+use crate::attributes::notification::NotificationAttributeID;
+use crate::attributes::NotificationAttribute;
+use crate::characteristics::control_point::RequestedAttribute;
+use crate::characteristics::notification_source::Notification;
+use crate::client::DEFAULT_ATTRIBUTES;
+
+/// What to fetch for a notification.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FetchRule {
+    /// Fetch nothing.
+    Skip,
+    /// Fetch the given attributes.
+    Fetch(Vec<RequestedAttribute>),
+}
+
+impl FetchRule {
+    /// Returns the attributes to fetch, which is empty for `FetchRule::Skip`.
+    pub fn attributes(&self) -> &[RequestedAttribute] {
+        match self {
+            FetchRule::Skip => &[],
+            FetchRule::Fetch(attributes) => attributes,
+        }
+    }
+}
+
+/// The `FetchPolicy` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::characteristics::control_point::RequestedAttribute;
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::client::policy::{FetchPolicy, FetchRule};
+/// let policy = FetchPolicy::default()
+///     .with_category(CategoryID::IncomingCall, FetchRule::Fetch(vec![RequestedAttribute::Title(32)]))
+///     .with_skip_silent(true);
+///
+/// let mut notification = Notification {
+///     event_id: EventID::NotificationAdded,
+///     event_flags: EventFlag::empty(),
+///     category_id: CategoryID::IncomingCall,
+///     category_count: 1,
+///     notification_uid: 1,
+/// };
+/// assert_eq!(policy.rule_for(&notification), FetchRule::Fetch(vec![RequestedAttribute::Title(32)]));
+///
+/// notification.event_flags = EventFlag::Silent;
+/// assert_eq!(policy.rule_for(&notification), FetchRule::Skip);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct FetchPolicy {
+    default: FetchRule,
+    categories: HashMap<CategoryID, FetchRule>,
+    apps: HashMap<String, FetchRule>,
+    skip_silent: bool,
+}
+
+impl Default for FetchPolicy {
+    /// Creates a `FetchPolicy` that fetches `DEFAULT_ATTRIBUTES` for every notification.
+    fn default() -> Self {
+        FetchPolicy::new(FetchRule::Fetch(DEFAULT_ATTRIBUTES.to_vec()))
+    }
+}
+
+impl FetchPolicy {
+    /// Creates a `FetchPolicy` applying `default` to every notification.
+    pub fn new(default: FetchRule) -> FetchPolicy {
+        FetchPolicy {
+            default,
+            categories: HashMap::new(),
+            apps: HashMap::new(),
+            skip_silent: false,
+        }
+    }
+
+    /// Sets the rule for notifications without a more specific one.
+    pub fn with_default(mut self, rule: FetchRule) -> FetchPolicy {
+        self.default = rule;
+        self
+    }
+
+    /// Sets the rule for notifications of the given category.
+    pub fn with_category(mut self, category_id: CategoryID, rule: FetchRule) -> FetchPolicy {
+        self.categories.insert(category_id, rule);
+        self
+    }
+
+    /// Sets the rule for notifications posted by the given app, taking precedence
+    /// over the rule for their category. See `retain` for how a rule asking for
+    /// fewer attributes than the first fetch is applied.
+    pub fn with_app(mut self, app_identifier: String, rule: FetchRule) -> FetchPolicy {
+        self.apps.insert(app_identifier, rule);
+        self
+    }
+
+    /// Sets whether notifications flagged `Silent` are not fetched at all.
+    pub fn with_skip_silent(mut self, skip_silent: bool) -> FetchPolicy {
+        self.skip_silent = skip_silent;
+        self
+    }
+
+    /// Decides what to fetch first for an added or modified notification.
+    ///
+    /// While per-app overrides are configured, `AppIdentifier` is added to the
+    /// attributes so that `follow_up` can apply them. A skipped notification is
+    /// never fetched, so per-app overrides cannot apply to it.
+    pub fn rule_for(&self, notification: &Notification) -> FetchRule {
+        if self.skip_silent && notification.event_flags.contains(EventFlag::Silent) {
+            return FetchRule::Skip;
+        }
+
+        let rule = self
+            .categories
+            .get(&notification.category_id)
+            .unwrap_or(&self.default);
+
+        match rule {
+            FetchRule::Skip => FetchRule::Skip,
+            FetchRule::Fetch(attributes) => {
+                let mut attributes = attributes.clone();
+
+                if !self.apps.is_empty() && !attributes.contains(&RequestedAttribute::AppIdentifier) {
+                    attributes.insert(0, RequestedAttribute::AppIdentifier);
+                }
+
+                FetchRule::Fetch(attributes)
+            },
+        }
+    }
+
+    /// Decides what to fetch in addition to `fetched` once a notification's app
+    /// is known, returning `None` if the first fetch was enough.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::control_point::RequestedAttribute;
+    /// # use ancs::client::policy::{FetchPolicy, FetchRule};
+    /// let policy = FetchPolicy::new(FetchRule::Fetch(vec![RequestedAttribute::Title(32)]))
+    ///     .with_app(
+    ///         "com.apple.MobileSMS".to_string(),
+    ///         FetchRule::Fetch(vec![RequestedAttribute::Title(32), RequestedAttribute::Message(128)]),
+    ///     );
+    ///
+    /// let fetched = [RequestedAttribute::AppIdentifier, RequestedAttribute::Title(32)];
+    ///
+    /// assert_eq!(policy.follow_up("com.apple.MobileSMS", &fetched), Some(vec![RequestedAttribute::Message(128)]));
+    /// assert_eq!(policy.follow_up("com.apple.mobilemail", &fetched), None);
+    /// ```
+    pub fn follow_up(&self, app_identifier: &str, fetched: &[RequestedAttribute]) -> Option<Vec<RequestedAttribute>> {
+        let missing: Vec<RequestedAttribute> = self
+            .apps
+            .get(app_identifier)?
+            .attributes()
+            .iter()
+            .filter(|attribute| !fetched.contains(attribute))
+            .copied()
+            .collect();
+
+        if missing.is_empty() {
+            return None;
+        }
+
+        Some(missing)
+    }
+
+    /// Drops the fetched `attributes` that the rule for the given app does not
+    /// ask for, keeping `AppIdentifier` so that the notification still names its
+    /// app. Does nothing for an app without a rule of its own.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::NotificationAttribute;
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// # use ancs::characteristics::control_point::RequestedAttribute;
+    /// # use ancs::client::policy::{FetchPolicy, FetchRule};
+    /// let policy = FetchPolicy::new(FetchRule::Fetch(vec![RequestedAttribute::Title(32)]))
+    ///     .with_app("com.example.noisy".to_string(), FetchRule::Skip);
+    ///
+    /// let attribute = |id, value: &str| NotificationAttribute {
+    ///     id,
+    ///     length: value.len() as u16,
+    ///     value: Some(value.to_string()),
+    /// };
+    /// let mut attributes = vec![
+    ///     attribute(NotificationAttributeID::AppIdentifier, "com.example.noisy"),
+    ///     attribute(NotificationAttributeID::Title, "Sale!"),
+    /// ];
+    ///
+    /// policy.retain("com.example.noisy", &mut attributes);
+    /// assert_eq!(attributes.len(), 1);
+    /// assert_eq!(attributes[0].id, NotificationAttributeID::AppIdentifier);
+    /// ```
+    pub fn retain(&self, app_identifier: &str, attributes: &mut Vec<NotificationAttribute>) {
+        let rule = match self.apps.get(app_identifier) {
+            Some(rule) => rule,
+            None => return,
+        };
+
+        attributes.retain(|attribute| {
+            attribute.id == NotificationAttributeID::AppIdentifier
+                || rule.attributes().iter().any(|requested| requested.id() == attribute.id)
+        });
+    }
+}
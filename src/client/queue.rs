@@ -0,0 +1,330 @@
+//! ## Command Queue
+//!
+//! ANCS processes one Control Point command at a time, and its Data Source reply
+//! only carries the `CommandID` and the `notification_uid` or `app_identifier` it
+//! answers. The `CommandQueue` serializes requests so that a single command is
+//! ever outstanding, correlates every Data Source fragment with that command, and
+//! times it out using an injectable `Clock` if the Notification Provider never
+//! finishes answering.
+//!
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::characteristics::control_point::*;
+use crate::characteristics::data_source::*;
+use crate::client::clock::{Clock, SystemClock};
+use crate::{Error, ParseOptions};
+
+/// How long a command may remain outstanding before it is timed out by default.
+/// This matches the 30 second ATT transaction timeout.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How a command left the `CommandQueue`, together with the tag it was pushed with.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Completion<T> {
+    /// The Notification Provider answered the command on the Data Source.
+    Response {
+        request: ControlPointRequest,
+        response: DataSourceResponse,
+        tag: T,
+    },
+    /// The Notification Provider accepted an action, which has no Data Source reply.
+    Accepted {
+        request: PerformNotificationActionRequest,
+        tag: T,
+    },
+    /// The Notification Provider rejected the Control Point write.
+    Rejected {
+        outcome: ControlPointOutcome<ControlPointRequest>,
+        tag: T,
+    },
+    /// The command was not answered before its deadline.
+    TimedOut {
+        request: ControlPointRequest,
+        tag: T,
+    },
+}
+
+impl<T> Completion<T> {
+    /// Returns the tag the completed command was pushed with.
+    pub fn tag(&self) -> &T {
+        match self {
+            Completion::Response { tag, .. } => tag,
+            Completion::Accepted { tag, .. } => tag,
+            Completion::Rejected { tag, .. } => tag,
+            Completion::TimedOut { tag, .. } => tag,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Queued<T> {
+    request: ControlPointRequest,
+    bytes: Vec<u8>,
+    tag: T,
+}
+
+/// A command that has been handed out for writing and is awaiting its reply.
+#[derive(Debug, PartialEq, Clone)]
+struct Outstanding<T> {
+    queued: Queued<T>,
+    reassembler: Option<DataSourceReassembler>,
+    deadline: Instant,
+}
+
+/// The `CommandQueue` type. See [the module level documentation](index.html) for more.
+///
+/// Every command carries a caller-defined tag of type `T`, handed back in its
+/// `Completion`, so the caller can remember why the command was sent.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use ancs::characteristics::control_point::GetNotificationAttributesRequest;
+/// # use ancs::characteristics::control_point::RequestedAttribute;
+/// # use ancs::client::clock::ManualClock;
+/// # use ancs::client::queue::{CommandQueue, Completion};
+/// let clock = ManualClock::new();
+/// let mut queue = CommandQueue::new(clock.clone()).with_timeout(Duration::from_secs(5));
+///
+/// for notification_uid in [1, 2] {
+///     let request = GetNotificationAttributesRequest {
+///         notification_uid,
+///         attribute_ids: vec![RequestedAttribute::Title(8)],
+///     };
+///     queue.push(request.into(), notification_uid).unwrap();
+/// }
+///
+/// // Only the first command is handed out until it has been answered
+/// assert_eq!(queue.poll_transmit(), Some(vec![0, 1, 0, 0, 0, 1, 8, 0]));
+/// assert_eq!(queue.poll_transmit(), None);
+/// queue.handle_write_result(Ok(()));
+///
+/// // A reply for another notification is rejected
+/// assert!(queue.handle_data_source(&[0, 9, 0, 0, 0, 1, 0, 0]).is_err());
+///
+/// // The Notification Provider never answers, so the command times out
+/// clock.advance(Duration::from_secs(5));
+/// match queue.handle_timeout() {
+///     Some(Completion::TimedOut { tag, .. }) => assert_eq!(tag, 1),
+///     completion => panic!("unexpected completion {:?}", completion),
+/// }
+///
+/// assert_eq!(queue.poll_transmit(), Some(vec![0, 2, 0, 0, 0, 1, 8, 0]));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct CommandQueue<T, C = SystemClock> {
+    clock: C,
+    timeout: Duration,
+    options: ParseOptions,
+    queued: VecDeque<Queued<T>>,
+    outstanding: Option<Outstanding<T>>,
+}
+
+impl<T> Default for CommandQueue<T> {
+    fn default() -> Self {
+        CommandQueue::new(SystemClock)
+    }
+}
+
+impl<T, C: Clock> CommandQueue<T, C> {
+    /// Creates an empty `CommandQueue` reading time from `clock`.
+    pub fn new(clock: C) -> CommandQueue<T, C> {
+        CommandQueue {
+            clock,
+            timeout: DEFAULT_TIMEOUT,
+            options: ParseOptions::default(),
+            queued: VecDeque::new(),
+            outstanding: None,
+        }
+    }
+
+    /// Sets how long a command may remain outstanding before it is timed out.
+    pub fn with_timeout(mut self, timeout: Duration) -> CommandQueue<T, C> {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the `ParseOptions` used for the Data Source replies.
+    pub fn with_options(mut self, options: ParseOptions) -> CommandQueue<T, C> {
+        self.options = options;
+        self
+    }
+
+    /// Returns the `Clock` the queue reads time from.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Encodes and queues a request behind any command already queued.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::characteristics::control_point::GetAppAttributesRequest;
+    /// # use ancs::client::queue::CommandQueue;
+    /// let mut queue = CommandQueue::default();
+    /// let request = GetAppAttributesRequest {
+    ///     app_identifier: String::new(),
+    ///     attribute_ids: vec![],
+    /// };
+    ///
+    /// assert_eq!(queue.push(request.into(), ()), Err(Error::InvalidAppIdentifier));
+    /// assert_eq!(queue.is_idle(), true);
+    /// ```
+    pub fn push(&mut self, request: ControlPointRequest, tag: T) -> Result<(), Error> {
+        let bytes: Vec<u8> = request.clone().try_into()?;
+
+        self.queued.push_back(Queued {
+            request,
+            bytes,
+            tag,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the next Control Point write to send, if no command is outstanding.
+    ///
+    /// The returned command is outstanding from this point on and times out once
+    /// the configured timeout has elapsed without it completing.
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        if self.outstanding.is_some() {
+            return None;
+        }
+
+        let queued = self.queued.pop_front()?;
+        let bytes = queued.bytes.clone();
+        let reassembler = DataSourceReassembler::new(&queued.request)
+            .map(|reassembler| reassembler.with_options(self.options))
+            .ok();
+
+        self.outstanding = Some(Outstanding {
+            queued,
+            reassembler,
+            deadline: self.clock.now() + self.timeout,
+        });
+
+        Some(bytes)
+    }
+
+    /// Handles the result of the Control Point write handed out by `poll_transmit`.
+    ///
+    /// Commands with a Data Source reply stay outstanding when the write succeeds.
+    pub fn handle_write_result(&mut self, result: Result<(), ControlPointError>) -> Option<Completion<T>> {
+        let outstanding = self.outstanding.take()?;
+
+        match result {
+            Err(error) => Some(Completion::Rejected {
+                outcome: ControlPointOutcome::rejected(outstanding.queued.request, error),
+                tag: outstanding.queued.tag,
+            }),
+            Ok(()) => match outstanding.queued.request {
+                // Actions have no Data Source reply, so they are done once accepted.
+                ControlPointRequest::PerformNotificationAction(request) => Some(Completion::Accepted {
+                    request,
+                    tag: outstanding.queued.tag,
+                }),
+                _ => {
+                    self.outstanding = Some(outstanding);
+                    None
+                },
+            },
+        }
+    }
+
+    /// Handles a Data Source fragment, completing the outstanding command once its
+    /// reply is whole.
+    ///
+    /// Fragments that answer a different command, notification or app, such as
+    /// late replies to a command that already timed out, are rejected without
+    /// changing the queue's state.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::client::queue::CommandQueue;
+    /// let mut queue: CommandQueue<()> = CommandQueue::default();
+    ///
+    /// assert_eq!(queue.handle_data_source(&[0, 1, 0, 0, 0]), Err(Error::NoOutstandingCommand));
+    /// ```
+    pub fn handle_data_source(&mut self, bytes: &[u8]) -> Result<Option<Completion<T>>, Error> {
+        let reassembler = self
+            .outstanding
+            .as_mut()
+            .and_then(|outstanding| outstanding.reassembler.as_mut())
+            .ok_or(Error::NoOutstandingCommand)?;
+
+        let response = match reassembler.push(bytes)? {
+            Some(response) => response,
+            None => return Ok(None),
+        };
+
+        let outstanding = self.outstanding.take().ok_or(Error::NoOutstandingCommand)?;
+
+        Ok(Some(Completion::Response {
+            request: outstanding.queued.request,
+            response,
+            tag: outstanding.queued.tag,
+        }))
+    }
+
+    /// Returns when the outstanding command times out, if one is outstanding.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        self.outstanding.as_ref().map(|outstanding| outstanding.deadline)
+    }
+
+    /// Times out the outstanding command if its deadline has passed, freeing the
+    /// queue for the next command.
+    pub fn handle_timeout(&mut self) -> Option<Completion<T>> {
+        let deadline = self.poll_timeout()?;
+
+        if self.clock.now() < deadline {
+            return None;
+        }
+
+        let outstanding = self.outstanding.take()?;
+
+        Some(Completion::TimedOut {
+            request: outstanding.queued.request,
+            tag: outstanding.queued.tag,
+        })
+    }
+
+    /// Returns the outstanding request and its tag, if any.
+    pub fn outstanding(&self) -> Option<(&ControlPointRequest, &T)> {
+        self.outstanding
+            .as_ref()
+            .map(|outstanding| (&outstanding.queued.request, &outstanding.queued.tag))
+    }
+
+    /// Returns the tag of the outstanding command, if any, so it can be updated
+    /// while the reply is in flight.
+    pub fn outstanding_tag_mut(&mut self) -> Option<&mut T> {
+        self.outstanding.as_mut().map(|outstanding| &mut outstanding.queued.tag)
+    }
+
+    /// Keeps only the queued commands for which `keep` returns `true`. The
+    /// outstanding command is not affected.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&ControlPointRequest, &T) -> bool,
+    {
+        self.queued.retain(|queued| keep(&queued.request, &queued.tag));
+    }
+
+    /// Returns the number of commands waiting behind the outstanding one.
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Determines if no command is waiting behind the outstanding one.
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Determines if no command is queued or outstanding.
+    pub fn is_idle(&self) -> bool {
+        self.queued.is_empty() && self.outstanding.is_none()
+    }
+}
@@ -0,0 +1,330 @@
+//! ## Rules
+//!
+//! Devices often need to suppress or reroute notifications depending on the app
+//! that posted them, their `CategoryID`, their `EventFlag`s or the text of their
+//! `Title` and `Message`. A `RuleSet` declares this as a list of `Rule`s, each
+//! pairing a `Condition` with a `RuleAction`, and evaluates them against a
+//! `Notification` and its fetched `NotificationAttribute`s into a `Verdict`.
+//!
+//! With the `serde` feature enabled a `RuleSet` can be loaded from any format
+//! supported by `serde`, such as TOML or JSON:
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! # use ancs::client::rules::{RuleAction, RuleSet};
+//! let rules: RuleSet = toml::from_str(r#"
+//!     [[rules]]
+//!     name = "mute marketing"
+//!     when = { app_identifier = "com.example.*", title = "*sale*" }
+//!     then = "Drop"
+//!
+//!     [[rules]]
+//!     when = { categories = ["IncomingCall"], flags = "Important" }
+//!     then = { Tag = "vip" }
+//! "#).unwrap();
+//!
+//! assert_eq!(rules.rules.len(), 2);
+//! assert_eq!(rules.rules[1].then, RuleAction::Tag("vip".to_string()));
+//!
+//! let json = r#"{ "rules": [{ "when": { "flags": "Silent" }, "then": { "SetPriority": "PreExisting" } }] }"#;
+//! let rules: RuleSet = serde_json::from_str(json).unwrap();
+//! assert_eq!(rules.rules.len(), 1);
+//! # }
+//! ```
+//!
+use crate::attributes::action::ActionID;
+use crate::attributes::category::CategoryID;
+use crate::attributes::event::EventFlag;
+use crate::attributes::notification::NotificationAttributeID;
+use crate::attributes::NotificationAttribute;
+use crate::characteristics::control_point::PerformNotificationActionRequest;
+use crate::characteristics::notification_source::Notification;
+use crate::client::scheduler::Priority;
+
+/// A case-insensitive glob pattern, where `*` matches any run of characters and
+/// `?` matches a single character.
+///
+/// # Examples
+/// ```
+/// # use ancs::client::rules::Pattern;
+/// let pattern = Pattern::new("com.apple.*".to_string());
+///
+/// assert_eq!(pattern.matches("com.apple.MobileSMS"), true);
+/// assert_eq!(pattern.matches("com.example.app"), false);
+/// assert_eq!(Pattern::new("*SALE?".to_string()).matches("Big sale!"), true);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Pattern(String);
+
+impl Pattern {
+    /// Creates a `Pattern` from its glob syntax.
+    pub fn new(pattern: String) -> Pattern {
+        Pattern(pattern)
+    }
+
+    /// Returns the glob the pattern was created from.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Determines if the whole of `text` matches the pattern, ignoring case.
+    pub fn matches(&self, text: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().flat_map(char::to_lowercase).collect();
+        let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+
+        let (mut p, mut t) = (0, 0);
+        // The position of the last `*` and of the text it was tried against.
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while t < text.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, t));
+                    p += 1;
+                },
+                Some(c) if *c == '?' || *c == text[t] => {
+                    p += 1;
+                    t += 1;
+                },
+                _ => match backtrack {
+                    Some((star, matched)) => {
+                        p = star + 1;
+                        t = matched + 1;
+                        backtrack = Some((star, matched + 1));
+                    },
+                    None => return false,
+                },
+            }
+        }
+
+        pattern[p..].iter().all(|c| *c == '*')
+    }
+}
+
+/// What a notification must look like for a `Rule` to apply. Every condition
+/// that is set must hold; a `Condition` with none set matches every notification.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Condition {
+    /// The app that posted the notification.
+    pub app_identifier: Option<Pattern>,
+    /// The categories the notification may belong to, any if empty.
+    pub categories: Vec<CategoryID>,
+    /// Flags that must all be set.
+    pub flags: Option<EventFlag>,
+    /// Flags that must all be clear.
+    pub without_flags: Option<EventFlag>,
+    /// The notification's `Title`.
+    pub title: Option<Pattern>,
+    /// The notification's `Message`.
+    pub message: Option<Pattern>,
+}
+
+impl Condition {
+    /// Determines if a notification and its fetched attributes satisfy the
+    /// condition. Patterns on attributes that were not fetched never match.
+    pub fn matches(&self, notification: &Notification, attributes: &[NotificationAttribute]) -> bool {
+        let matches_attribute = |pattern: &Option<Pattern>, id: NotificationAttributeID| match pattern {
+            Some(pattern) => attributes
+                .iter()
+                .find(|attribute| attribute.id == id)
+                .and_then(|attribute| attribute.value.as_deref())
+                .is_some_and(|value| pattern.matches(value)),
+            None => true,
+        };
+
+        (self.categories.is_empty() || self.categories.contains(&notification.category_id))
+            && self
+                .flags
+                .map(|flags| notification.event_flags.contains(flags))
+                .unwrap_or(true)
+            && self
+                .without_flags
+                .map(|flags| !notification.event_flags.intersects(flags))
+                .unwrap_or(true)
+            && matches_attribute(&self.app_identifier, NotificationAttributeID::AppIdentifier)
+            && matches_attribute(&self.title, NotificationAttributeID::Title)
+            && matches_attribute(&self.message, NotificationAttributeID::Message)
+    }
+}
+
+/// What a matching `Rule` does to a notification.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RuleAction {
+    /// Suppresses the notification. No further rules are evaluated.
+    Drop,
+    /// Presents the notification without an alert.
+    MarkSilent,
+    /// Changes the notification's priority.
+    SetPriority(Priority),
+    /// Dismisses the notification on the Notification Provider with
+    /// `ActionID::Negative`, using the request built by `Verdict::dismissal`.
+    Dismiss,
+    /// Attaches a tag, for example to route the notification.
+    Tag(String),
+}
+
+/// A `Condition` and the `RuleAction` taken when it matches.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    /// An optional name, for diagnostics.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub when: Condition,
+    pub then: RuleAction,
+}
+
+/// The combined outcome of every `Rule` matching a notification.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Verdict {
+    /// A `RuleAction::Drop` matched.
+    pub drop: bool,
+    /// A `RuleAction::MarkSilent` matched.
+    pub silent: bool,
+    /// A `RuleAction::Dismiss` matched.
+    pub dismiss: bool,
+    /// The priority set by the last matching `RuleAction::SetPriority`.
+    pub priority: Option<Priority>,
+    /// The tags of every matching `RuleAction::Tag`, without duplicates.
+    pub tags: Vec<String>,
+}
+
+impl Verdict {
+    /// Builds the request dismissing `notification` if a `RuleAction::Dismiss`
+    /// matched. Returns `None` otherwise, or if the notification does not offer
+    /// a negative action, which the Notification Provider would reject.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::rules::{Condition, Rule, RuleAction, RuleSet};
+    /// let rules = RuleSet::new(vec![Rule {
+    ///     name: None,
+    ///     when: Condition {
+    ///         categories: vec![CategoryID::News],
+    ///         ..Condition::default()
+    ///     },
+    ///     then: RuleAction::Dismiss,
+    /// }]);
+    ///
+    /// let mut notification = Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::NegativeAction,
+    ///     category_id: CategoryID::News,
+    ///     category_count: 1,
+    ///     notification_uid: 9,
+    /// };
+    ///
+    /// let request = rules.evaluate(&notification, &[]).dismissal(&notification).unwrap();
+    /// assert_eq!((request.notification_uid, request.action_id), (9, ActionID::Negative));
+    ///
+    /// notification.event_flags = EventFlag::empty();
+    /// assert_eq!(rules.evaluate(&notification, &[]).dismissal(&notification), None);
+    /// ```
+    pub fn dismissal(&self, notification: &Notification) -> Option<PerformNotificationActionRequest> {
+        if !self.dismiss || !notification.event_flags.contains(EventFlag::NegativeAction) {
+            return None;
+        }
+
+        Some(PerformNotificationActionRequest {
+            notification_uid: notification.notification_uid,
+            action_id: ActionID::Negative,
+        })
+    }
+}
+
+/// The `RuleSet` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::NotificationAttribute;
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::attributes::notification::NotificationAttributeID;
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::client::rules::{Condition, Pattern, Rule, RuleAction, RuleSet};
+/// let rules = RuleSet::new(vec![
+///     Rule {
+///         name: None,
+///         when: Condition {
+///             title: Some(Pattern::new("*newsletter*".to_string())),
+///             ..Condition::default()
+///         },
+///         then: RuleAction::MarkSilent,
+///     },
+///     Rule {
+///         name: None,
+///         when: Condition {
+///             categories: vec![CategoryID::Email],
+///             ..Condition::default()
+///         },
+///         then: RuleAction::Tag("mail".to_string()),
+///     },
+/// ]);
+///
+/// let notification = Notification {
+///     event_id: EventID::NotificationAdded,
+///     event_flags: EventFlag::empty(),
+///     category_id: CategoryID::Email,
+///     category_count: 1,
+///     notification_uid: 1,
+/// };
+/// let attributes = vec![NotificationAttribute {
+///     id: NotificationAttributeID::Title,
+///     length: 17,
+///     value: Some("Weekly Newsletter".to_string()),
+/// }];
+///
+/// let verdict = rules.evaluate(&notification, &attributes);
+///
+/// assert_eq!(verdict.silent, true);
+/// assert_eq!(verdict.tags, vec!["mail".to_string()]);
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleSet {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Creates a `RuleSet` evaluating `rules` in order.
+    pub fn new(rules: Vec<Rule>) -> RuleSet {
+        RuleSet { rules }
+    }
+
+    /// Evaluates every rule in order against a notification and its fetched
+    /// attributes, stopping at the first `RuleAction::Drop`.
+    pub fn evaluate(&self, notification: &Notification, attributes: &[NotificationAttribute]) -> Verdict {
+        let mut verdict = Verdict::default();
+
+        for rule in self.rules.iter().filter(|rule| rule.when.matches(notification, attributes)) {
+            match &rule.then {
+                RuleAction::Drop => {
+                    verdict.drop = true;
+                    break;
+                },
+                RuleAction::MarkSilent => verdict.silent = true,
+                RuleAction::SetPriority(priority) => verdict.priority = Some(*priority),
+                RuleAction::Dismiss => verdict.dismiss = true,
+                RuleAction::Tag(tag) => {
+                    if !verdict.tags.contains(tag) {
+                        verdict.tags.push(tag.clone());
+                    }
+                },
+            }
+        }
+
+        verdict
+    }
+}
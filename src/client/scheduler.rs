@@ -0,0 +1,250 @@
+//! ## Fetch Scheduler
+//!
+//! When a Notification Consumer connects, iOS replays every existing notification
+//! with `EventFlag::PreExisting`, often hundreds of them. Fetching their attributes
+//! in arrival order keeps the Control Point busy for seconds while new and more
+//! urgent notifications wait. The `FetchScheduler` holds pending fetches back,
+//! releases them by `Priority` and then in arrival order, and caps how many are
+//! in flight at once.
+//!
+use std::cmp::Reverse;
+
+use crate::attributes::category::CategoryID;
+use crate::attributes::event::{EventFlag, EventID};
+use crate::characteristics::control_point::{GetNotificationAttributesRequest, RequestedAttribute};
+use crate::characteristics::notification_source::Notification;
+
+/// The number of fetches in flight at once by default. ANCS answers one Control
+/// Point command at a time, so a single fetch keeps the link busy.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 1;
+
+/// How urgently a notification's attributes are fetched, from lowest to highest.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Priority {
+    /// A notification that existed before the Notification Consumer connected.
+    PreExisting,
+    /// A notification posted since the Notification Consumer connected.
+    New,
+    /// A notification flagged `Important`.
+    Important,
+    /// An incoming call, which is only actionable while it rings.
+    IncomingCall,
+}
+
+impl Priority {
+    /// Ranks a Notification Source event.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::scheduler::Priority;
+    /// let notification = Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::PreExisting | EventFlag::Important,
+    ///     category_id: CategoryID::Email,
+    ///     category_count: 1,
+    ///     notification_uid: 1,
+    /// };
+    ///
+    /// assert_eq!(Priority::of(&notification), Priority::Important);
+    /// ```
+    pub fn of(notification: &Notification) -> Priority {
+        if notification.category_id == CategoryID::IncomingCall {
+            Priority::IncomingCall
+        } else if notification.event_flags.contains(EventFlag::Important) {
+            Priority::Important
+        } else if notification.event_flags.contains(EventFlag::PreExisting) {
+            Priority::PreExisting
+        } else {
+            Priority::New
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Pending {
+    priority: Priority,
+    sequence: u64,
+    notification: Notification,
+    attribute_ids: Vec<RequestedAttribute>,
+}
+
+impl Pending {
+    /// Orders pending fetches by priority, then by arrival.
+    fn key(&self) -> (Priority, Reverse<u64>) {
+        (self.priority, Reverse(self.sequence))
+    }
+}
+
+/// The `FetchScheduler` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::characteristics::control_point::RequestedAttribute;
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::client::scheduler::FetchScheduler;
+/// let mut scheduler = FetchScheduler::new();
+/// let attributes = vec![RequestedAttribute::Title(32)];
+///
+/// for (notification_uid, event_flags, category_id) in [
+///     (1, EventFlag::PreExisting, CategoryID::Email),
+///     (2, EventFlag::empty(), CategoryID::Social),
+///     (3, EventFlag::Important, CategoryID::Schedule),
+///     (4, EventFlag::empty(), CategoryID::IncomingCall),
+/// ] {
+///     let notification = Notification {
+///         event_id: EventID::NotificationAdded,
+///         event_flags,
+///         category_id,
+///         category_count: 1,
+///         notification_uid,
+///     };
+///     scheduler.schedule(notification, attributes.clone());
+/// }
+///
+/// let mut order = Vec::new();
+/// while let Some((notification, _)) = scheduler.poll() {
+///     // Only one fetch is in flight until it completes
+///     assert_eq!(scheduler.poll(), None);
+///     scheduler.complete(notification.notification_uid);
+///     order.push(notification.notification_uid);
+/// }
+///
+/// assert_eq!(order, vec![4, 3, 2, 1]);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct FetchScheduler {
+    max_in_flight: usize,
+    pending: Vec<Pending>,
+    in_flight: Vec<u32>,
+    sequence: u64,
+}
+
+impl Default for FetchScheduler {
+    fn default() -> Self {
+        FetchScheduler::new()
+    }
+}
+
+impl FetchScheduler {
+    /// Creates an empty `FetchScheduler` allowing `DEFAULT_MAX_IN_FLIGHT` fetches at once.
+    pub fn new() -> FetchScheduler {
+        FetchScheduler {
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            pending: Vec::new(),
+            in_flight: Vec::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Sets how many fetches may be in flight at once. At least one always is.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> FetchScheduler {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Schedules fetching `attribute_ids` for a Notification Source event.
+    ///
+    /// A fetch still pending for the same notification is replaced, keeping its
+    /// place in line unless the new event ranks higher. A pending addition stays
+    /// an addition, as the notification was never reported.
+    pub fn schedule(&mut self, notification: Notification, attribute_ids: Vec<RequestedAttribute>) {
+        let priority = Priority::of(&notification);
+        let uid = notification.notification_uid;
+
+        if let Some(pending) = self
+            .pending
+            .iter_mut()
+            .find(|pending| pending.notification.notification_uid == uid)
+        {
+            let event_id = pending.notification.event_id;
+
+            pending.priority = pending.priority.max(priority);
+            pending.notification = notification;
+            if event_id == EventID::NotificationAdded {
+                pending.notification.event_id = event_id;
+            }
+            pending.attribute_ids = attribute_ids;
+            return;
+        }
+
+        self.pending.push(Pending {
+            priority,
+            sequence: self.sequence,
+            notification,
+            attribute_ids,
+        });
+        self.sequence += 1;
+    }
+
+    /// Releases the most urgent pending fetch, unless the maximum number of
+    /// fetches is already in flight. Every released fetch must be followed by a
+    /// call to `complete` once it has been answered, rejected or abandoned.
+    pub fn poll(&mut self) -> Option<(Notification, GetNotificationAttributesRequest)> {
+        if self.in_flight.len() >= self.max_in_flight {
+            return None;
+        }
+
+        let index = self
+            .pending
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, pending)| pending.key())
+            .map(|(index, _)| index)?;
+
+        let pending = self.pending.remove(index);
+        let uid = pending.notification.notification_uid;
+        self.in_flight.push(uid);
+
+        let request = GetNotificationAttributesRequest {
+            notification_uid: uid,
+            attribute_ids: pending.attribute_ids,
+        };
+
+        Some((pending.notification, request))
+    }
+
+    /// Frees the in-flight slot of a fetch released by `poll`.
+    pub fn complete(&mut self, notification_uid: u32) {
+        if let Some(index) = self.in_flight.iter().position(|uid| *uid == notification_uid) {
+            self.in_flight.remove(index);
+        }
+    }
+
+    /// Drops the pending fetch for a notification, for example because it was
+    /// removed, returning whether there was one. A fetch already in flight is
+    /// not affected.
+    pub fn cancel(&mut self, notification_uid: u32) -> bool {
+        let len = self.pending.len();
+        self.pending
+            .retain(|pending| pending.notification.notification_uid != notification_uid);
+        self.pending.len() != len
+    }
+
+    /// Drops every pending fetch and forgets those in flight, for example after
+    /// the Notification Provider disconnects.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.in_flight.clear();
+    }
+
+    /// Returns the number of fetches waiting to be released.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Determines if no fetch is waiting to be released.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns the number of fetches released and not yet completed.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+}
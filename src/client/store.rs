@@ -0,0 +1,372 @@
+//! ## Notification Store
+//!
+//! A Notification Source event only describes what happened to a notification;
+//! its content arrives separately as `NotificationAttribute`s on the Data Source.
+//! The `NotificationStore` keeps the current state of every notification the
+//! Notification Provider has reported, keyed by `notification_uid`, merging the
+//! two as they arrive. It can be bounded, in which case the least recently
+//! updated notification is evicted first, and it publishes every `Change` to
+//! its subscribers.
+//!
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::attributes::event::{EventFlag, EventID};
+use crate::attributes::notification::NotificationAttributeID;
+use crate::attributes::NotificationAttribute;
+use crate::characteristics::data_source::GetNotificationAttributesResponse;
+use crate::characteristics::notification_source::Notification;
+use crate::client::Event;
+
+/// The current state of a notification kept by a `NotificationStore`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TrackedNotification {
+    /// The latest Notification Source event received for the notification.
+    pub notification: Notification,
+    /// The attributes fetched so far, at most one per `NotificationAttributeID`.
+    pub attributes: Vec<NotificationAttribute>,
+}
+
+impl TrackedNotification {
+    /// Creates a `TrackedNotification` without any attributes.
+    pub fn new(notification: Notification) -> TrackedNotification {
+        TrackedNotification {
+            notification,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Returns the notification's `notification_uid`.
+    pub fn uid(&self) -> u32 {
+        self.notification.notification_uid
+    }
+
+    /// Returns the notification's latest `EventFlag`s.
+    pub fn flags(&self) -> EventFlag {
+        self.notification.event_flags
+    }
+
+    /// Determines if the notification should not produce an audible or visible alert.
+    pub fn is_silent(&self) -> bool {
+        self.flags().contains(EventFlag::Silent)
+    }
+
+    /// Determines if the notification was flagged as important.
+    pub fn is_important(&self) -> bool {
+        self.flags().contains(EventFlag::Important)
+    }
+
+    /// Determines if the notification existed before the Notification Consumer connected.
+    pub fn is_pre_existing(&self) -> bool {
+        self.flags().contains(EventFlag::PreExisting)
+    }
+
+    /// Returns the fetched attribute with the given `id`, if any.
+    pub fn attribute(&self, id: NotificationAttributeID) -> Option<&NotificationAttribute> {
+        self.attributes.iter().find(|attribute| attribute.id == id)
+    }
+
+    /// Returns the value of the fetched attribute with the given `id`, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::NotificationAttribute;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::attributes::notification::NotificationAttributeID;
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::store::TrackedNotification;
+    /// # let notification = Notification {
+    /// #     event_id: EventID::NotificationAdded,
+    /// #     event_flags: EventFlag::empty(),
+    /// #     category_id: CategoryID::Email,
+    /// #     category_count: 1,
+    /// #     notification_uid: 1,
+    /// # };
+    /// let mut tracked = TrackedNotification::new(notification);
+    ///
+    /// tracked.merge_attributes(vec![NotificationAttribute {
+    ///     id: NotificationAttributeID::Title,
+    ///     length: 5,
+    ///     value: Some("Hello".to_string()),
+    /// }]);
+    ///
+    /// assert_eq!(tracked.value(NotificationAttributeID::Title), Some("Hello"));
+    /// assert_eq!(tracked.value(NotificationAttributeID::Message), None);
+    /// ```
+    pub fn value(&self, id: NotificationAttributeID) -> Option<&str> {
+        self.attribute(id).and_then(|attribute| attribute.value.as_deref())
+    }
+
+    /// Returns the identifier of the app that posted the notification, if fetched.
+    pub fn app_identifier(&self) -> Option<&str> {
+        self.value(NotificationAttributeID::AppIdentifier)
+    }
+
+    /// Merges freshly fetched attributes, replacing any previously fetched
+    /// attribute with the same `NotificationAttributeID`.
+    pub fn merge_attributes(&mut self, attributes: Vec<NotificationAttribute>) {
+        for attribute in attributes {
+            match self.attributes.iter_mut().find(|existing| existing.id == attribute.id) {
+                Some(existing) => *existing = attribute,
+                None => self.attributes.push(attribute),
+            }
+        }
+    }
+}
+
+/// A change made to a `NotificationStore`, carrying the affected notification
+/// as it is after the change, or as it was when it left the store.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change {
+    /// A notification was added.
+    Added(TrackedNotification),
+    /// A notification's event, flags or category count changed.
+    Modified(TrackedNotification),
+    /// Attributes were merged into a notification.
+    AttributesUpdated(TrackedNotification),
+    /// The Notification Provider removed a notification.
+    Removed(TrackedNotification),
+    /// A notification was evicted to stay within the store's capacity.
+    Evicted(TrackedNotification),
+}
+
+/// The `NotificationStore` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::client::store::{Change, NotificationStore};
+/// let mut store = NotificationStore::new().with_capacity(2);
+/// let changes = store.subscribe();
+///
+/// for notification_uid in 1..=3 {
+///     store.apply(Notification {
+///         event_id: EventID::NotificationAdded,
+///         event_flags: EventFlag::Important,
+///         category_id: CategoryID::Email,
+///         category_count: notification_uid as u8,
+///         notification_uid,
+///     });
+/// }
+///
+/// // The oldest notification made way for the third one
+/// assert_eq!(store.len(), 2);
+/// assert_eq!(store.get(1), None);
+/// assert_eq!(store.get(3).map(|tracked| tracked.is_important()), Some(true));
+///
+/// let evicted: Vec<u32> = changes
+///     .try_iter()
+///     .filter_map(|change| match change {
+///         Change::Evicted(tracked) => Some(tracked.uid()),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(evicted, vec![1]);
+/// ```
+#[derive(Debug, Default)]
+pub struct NotificationStore {
+    capacity: Option<usize>,
+    entries: HashMap<u32, TrackedNotification>,
+    /// Notification UIDs from least to most recently updated.
+    recency: VecDeque<u32>,
+    subscribers: Vec<Sender<Change>>,
+}
+
+impl NotificationStore {
+    /// Creates an empty, unbounded `NotificationStore`.
+    pub fn new() -> NotificationStore {
+        NotificationStore::default()
+    }
+
+    /// Bounds the store to `capacity` notifications, evicting the least recently
+    /// updated notification when a new one would exceed it.
+    pub fn with_capacity(mut self, capacity: usize) -> NotificationStore {
+        self.capacity = Some(capacity);
+        self.evict();
+        self
+    }
+
+    /// Subscribes to every subsequent `Change`. Dropping the `Receiver` ends the
+    /// subscription.
+    pub fn subscribe(&mut self) -> Receiver<Change> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Applies a Notification Source event.
+    ///
+    /// A modification of an unknown notification adds it, as the Notification
+    /// Consumer may have missed the original addition. Attributes fetched for a
+    /// notification are kept across modifications until they are merged again.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::client::store::NotificationStore;
+    /// let mut store = NotificationStore::new();
+    /// let mut notification = Notification {
+    ///     event_id: EventID::NotificationAdded,
+    ///     event_flags: EventFlag::PreExisting | EventFlag::Silent,
+    ///     category_id: CategoryID::Social,
+    ///     category_count: 1,
+    ///     notification_uid: 4,
+    /// };
+    ///
+    /// store.apply(notification);
+    /// assert_eq!(store.get(4).map(|tracked| tracked.is_pre_existing()), Some(true));
+    ///
+    /// notification.event_id = EventID::NotificationModified;
+    /// notification.event_flags = EventFlag::PreExisting;
+    /// store.apply(notification);
+    /// assert_eq!(store.get(4).map(|tracked| tracked.is_silent()), Some(false));
+    ///
+    /// notification.event_id = EventID::NotificationRemoved;
+    /// store.apply(notification);
+    /// assert_eq!(store.is_empty(), true);
+    /// ```
+    pub fn apply(&mut self, notification: Notification) {
+        let uid = notification.notification_uid;
+
+        match notification.event_id {
+            EventID::NotificationAdded => {
+                let tracked = TrackedNotification::new(notification);
+                self.insert(tracked.clone());
+                self.publish(Change::Added(tracked));
+            },
+            EventID::NotificationModified => match self.entries.get_mut(&uid) {
+                Some(tracked) => {
+                    tracked.notification = notification;
+                    let tracked = tracked.clone();
+                    self.touch(uid);
+                    self.publish(Change::Modified(tracked));
+                },
+                None => {
+                    let tracked = TrackedNotification::new(notification);
+                    self.insert(tracked.clone());
+                    self.publish(Change::Added(tracked));
+                },
+            },
+            EventID::NotificationRemoved => {
+                if let Some(tracked) = self.remove(uid) {
+                    self.publish(Change::Removed(tracked));
+                }
+            },
+            EventID::Unknown(_) => (),
+        }
+    }
+
+    /// Merges fetched attributes into a notification. Attributes for a
+    /// notification that is not in the store are ignored, as it was removed or
+    /// evicted while they were in flight.
+    pub fn merge_attributes(&mut self, notification_uid: u32, attributes: Vec<NotificationAttribute>) {
+        let tracked = match self.entries.get_mut(&notification_uid) {
+            Some(tracked) => tracked,
+            None => return,
+        };
+
+        tracked.merge_attributes(attributes);
+        let tracked = tracked.clone();
+        self.touch(notification_uid);
+        self.publish(Change::AttributesUpdated(tracked));
+    }
+
+    /// Merges the attributes of a `GetNotificationAttributesResponse`.
+    pub fn apply_response(&mut self, response: GetNotificationAttributesResponse) {
+        self.merge_attributes(response.notification_uid, response.attribute_list);
+    }
+
+    /// Applies an `Event` produced by an `AncsClient`, ignoring events that do
+    /// not concern notifications.
+    pub fn apply_event(&mut self, event: &Event) {
+        match event {
+            Event::NotificationAdded { notification, attributes }
+            | Event::NotificationModified { notification, attributes } => {
+                self.apply(*notification);
+                self.merge_attributes(notification.notification_uid, attributes.clone());
+            },
+            Event::NotificationRemoved(notification) => self.apply(*notification),
+            Event::NotificationAttributes(response) => self.apply_response(response.clone()),
+            _ => (),
+        }
+    }
+
+    /// Returns the notification with the given `notification_uid`, if any.
+    pub fn get(&self, notification_uid: u32) -> Option<&TrackedNotification> {
+        self.entries.get(&notification_uid)
+    }
+
+    /// Determines if the store holds the notification with the given `notification_uid`.
+    pub fn contains(&self, notification_uid: u32) -> bool {
+        self.entries.contains_key(&notification_uid)
+    }
+
+    /// Returns the notifications from least to most recently updated.
+    pub fn iter(&self) -> impl Iterator<Item = &TrackedNotification> {
+        self.recency.iter().filter_map(|uid| self.entries.get(uid))
+    }
+
+    /// Returns the number of notifications in the store.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Determines if the store holds no notifications.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every notification without publishing any `Change`, for example
+    /// after the Notification Provider disconnects.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    fn insert(&mut self, tracked: TrackedNotification) {
+        let uid = tracked.uid();
+
+        self.entries.insert(uid, tracked);
+        self.touch(uid);
+        self.evict();
+    }
+
+    fn remove(&mut self, notification_uid: u32) -> Option<TrackedNotification> {
+        self.recency.retain(|uid| *uid != notification_uid);
+        self.entries.remove(&notification_uid)
+    }
+
+    fn touch(&mut self, notification_uid: u32) {
+        self.recency.retain(|uid| *uid != notification_uid);
+        self.recency.push_back(notification_uid);
+    }
+
+    fn evict(&mut self) {
+        let capacity = match self.capacity {
+            Some(capacity) => capacity,
+            None => return,
+        };
+
+        while self.entries.len() > capacity {
+            let evicted = self
+                .recency
+                .pop_front()
+                .and_then(|uid| self.entries.remove(&uid));
+
+            match evicted {
+                Some(tracked) => self.publish(Change::Evicted(tracked)),
+                None => break,
+            }
+        }
+    }
+
+    fn publish(&mut self, change: Change) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(change.clone()).is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+//! ## Error
+//!
+//! Every parse and encode path in this crate reports failures through the `Error`
+//! type defined here. Parsers are regular `nom` parsers that use `Error` as their
+//! error type, so they compose with `nom` combinators while still telling callers
+//! exactly what was wrong with the data they received.
+//!
+use std::fmt;
+
+use crate::attributes::action::ActionID;
+use crate::attributes::command::CommandID;
+use crate::attributes::notification::NotificationAttributeID;
+
+use nom::error::{ErrorKind, ParseError};
+use uuid::Uuid;
+
+/// The `Error` type. See [the module level documentation](index.html) for more.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Error {
+    /// The byte is not a `CategoryID` defined by the ANCS specification.
+    InvalidCategoryID(u8),
+    /// The byte is not an `EventID` defined by the ANCS specification.
+    InvalidEventID(u8),
+    /// The byte contains `EventFlag` bits reserved by the ANCS specification.
+    InvalidEventFlag(u8),
+    /// The byte is not a `CommandID` defined by the ANCS specification.
+    InvalidCommandID(u8),
+    /// The byte is not an `ActionID` defined by the ANCS specification.
+    InvalidActionID(u8),
+    /// The byte is not a `NotificationAttributeID` defined by the ANCS specification.
+    InvalidNotificationAttributeID(u8),
+    /// The byte is not an `AppAttributeID` defined by the ANCS specification.
+    InvalidAppAttributeID(u8),
+    /// The byte is not a Control Point error code defined by the ANCS specification.
+    InvalidControlPointError(u8),
+    /// A requested attribute requires a maximum length but none was given.
+    MissingMaxLength(NotificationAttributeID),
+    /// A requested attribute does not take a maximum length but one was given.
+    UnexpectedMaxLength(NotificationAttributeID),
+    /// An app identifier is empty or contains a NULL byte before its end.
+    InvalidAppIdentifier,
+    /// A string value is not valid UTF-8.
+    InvalidUtf8,
+    /// The input ended before a complete value could be read.
+    Truncated,
+    /// A declared length does not match the length of the data it describes.
+    LengthMismatch { expected: usize, actual: usize },
+    /// The command does not match the one expected, such as a Data Source fragment
+    /// answering a different command than the one outstanding.
+    UnexpectedCommandID(u8),
+    /// The command never produces a response on the Data Source.
+    NoDataSourceResponse(CommandID),
+    /// A Data Source fragment answers a request for a different notification.
+    UnexpectedNotificationUID(u32),
+    /// A Data Source fragment answers a request for a different app.
+    UnexpectedAppIdentifier,
+    /// A Data Source fragment carries this many bytes past the last requested attribute.
+    TrailingBytes(usize),
+    /// A Data Source fragment arrived after the response was already complete.
+    AlreadyComplete,
+    /// Bytes were received on a characteristic that is not part of ANCS.
+    UnknownCharacteristic(Uuid),
+    /// A Data Source fragment arrived while no command was awaiting a response.
+    NoOutstandingCommand,
+    /// The notification does not offer the action, as its `EventFlag`s lack it.
+    ActionUnavailable(ActionID),
+    /// No incoming call is ringing, so it cannot be answered or declined.
+    NoRingingCall,
+    /// The input could not be parsed for any other reason.
+    Parse(ErrorKind),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidCategoryID(id) => write!(f, "invalid category id {}", id),
+            Error::InvalidEventID(id) => write!(f, "invalid event id {}", id),
+            Error::InvalidEventFlag(flags) => write!(f, "invalid event flags {:#010b}", flags),
+            Error::InvalidCommandID(id) => write!(f, "invalid command id {}", id),
+            Error::InvalidActionID(id) => write!(f, "invalid action id {}", id),
+            Error::InvalidNotificationAttributeID(id) => write!(f, "invalid notification attribute id {}", id),
+            Error::InvalidAppAttributeID(id) => write!(f, "invalid app attribute id {}", id),
+            Error::InvalidControlPointError(code) => write!(f, "invalid control point error code {:#04X}", code),
+            Error::MissingMaxLength(id) => write!(f, "{:?} requires a maximum length", id),
+            Error::UnexpectedMaxLength(id) => write!(f, "{:?} does not take a maximum length", id),
+            Error::InvalidAppIdentifier => write!(f, "invalid app identifier"),
+            Error::InvalidUtf8 => write!(f, "invalid UTF-8 in attribute value"),
+            Error::Truncated => write!(f, "input ended unexpectedly"),
+            Error::LengthMismatch { expected, actual } => {
+                write!(f, "length mismatch: expected {} bytes, found {}", expected, actual)
+            },
+            Error::UnexpectedCommandID(id) => write!(f, "unexpected response for command id {}", id),
+            Error::NoDataSourceResponse(id) => write!(f, "{:?} has no data source response", id),
+            Error::UnexpectedNotificationUID(uid) => write!(f, "unexpected response for notification {}", uid),
+            Error::UnexpectedAppIdentifier => write!(f, "unexpected response for app identifier"),
+            Error::TrailingBytes(count) => write!(f, "{} unexpected bytes after response", count),
+            Error::AlreadyComplete => write!(f, "response is already complete"),
+            Error::UnknownCharacteristic(uuid) => write!(f, "unknown characteristic {}", uuid),
+            Error::NoOutstandingCommand => write!(f, "no command is awaiting a response"),
+            Error::ActionUnavailable(action_id) => write!(f, "notification does not offer action {:?}", action_id),
+            Error::NoRingingCall => write!(f, "no incoming call is ringing"),
+            Error::Parse(kind) => write!(f, "parse error: {}", kind.description()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl<I> ParseError<I> for Error {
+    /// Converts a `nom` error into an `Error`, treating the end of input as truncation
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::category::CategoryID;
+    /// let data: [u8; 0] = [];
+    ///
+    /// assert_eq!(CategoryID::parse(&data), Err(nom::Err::Error(Error::Truncated)));
+    /// ```
+    fn from_error_kind(_: I, kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Eof => Error::Truncated,
+            kind => Error::Parse(kind),
+        }
+    }
+
+    fn append(_: I, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl From<nom::Err<Error>> for Error {
+    /// Unwraps the `Error` carried by a `nom::Err`, so parse results can be used with `?`
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::Error;
+    /// # use ancs::attributes::category::CategoryID;
+    /// fn category(data: &[u8]) -> Result<CategoryID, Error> {
+    ///     let (_, category_id) = CategoryID::parse(data)?;
+    ///     Ok(category_id)
+    /// }
+    ///
+    /// assert_eq!(category(&[12]), Err(Error::InvalidCategoryID(12)));
+    /// ```
+    fn from(original: nom::Err<Error>) -> Error {
+        match original {
+            nom::Err::Incomplete(_) => Error::Truncated,
+            nom::Err::Error(error) | nom::Err::Failure(error) => error,
+        }
+    }
+}
@@ -5,15 +5,18 @@
 //! and `characteristics` that are used for interacting with Apple Notification Control Service
 //! as a client. These two modules contain all relevant components specified in
 //! the  Apple Notification Control Service protocol standard. This library is low-level
-//! and as such it is only concerned with handling serialization and deserialization of the 
-//! ANCS application protocol.
+//! and as such it is mostly concerned with handling serialization and deserialization of the 
+//! ANCS application protocol. The `client` module builds on them with a transport-agnostic
+//! Notification Consumer that drives the protocol without performing any I/O itself, and
+//! the `transport` module connects it to a GATT stack, or to an in-memory mock for testing.
+//! The `server` module simulates the other end, the Notification Provider.
 //! 
 //! ## Apple Notification Control Service Protocol
 //! 
 //! > The purpose of the Apple Notification Control Center Service is to give Bluetooth
-//! accessories (that connect to iOS devices through a Bluetooth low-energy link) a 
-//! simple convenient way to access many kinds of notifications that are generated on 
-//! iOS devices.
+//! > accessories (that connect to iOS devices through a Bluetooth low-energy link) a 
+//! > simple convenient way to access many kinds of notifications that are generated on 
+//! > iOS devices.
 //! 
 //! The ANCS protocol utilizes Bluetooth low-energy and a GATT Service, Characteristics and 
 //! Attributes to handle all data transport over Bluetooth low-energy. This library allows
@@ -21,6 +24,14 @@
 //! 
 pub mod attributes;
 pub mod characteristics;
+pub mod client;
+pub mod error;
+pub mod parse;
+pub mod server;
+pub mod transport;
+
+pub use error::Error;
+pub use parse::ParseOptions;
 use uuid::{uuid, Uuid};
 
 pub const APPLE_NOTIFICATION_CENTER_SERVICE_UUID: Uuid =
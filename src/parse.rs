@@ -0,0 +1,116 @@
+//! ## Parse
+//!
+//! Options shared by every parser in this crate. The plain `parse` functions use
+//! the defaults; the matching `parse_with` functions accept a `ParseOptions` for
+//! callers that need to change how unexpected or incomplete input is handled.
+//!
+use crate::Error;
+
+use nom::{
+    bytes::{complete as complete_bytes, streaming as streaming_bytes},
+    number::{complete, streaming},
+    IResult,
+};
+
+/// The `ParseOptions` type. See [the module level documentation](index.html) for more.
+///
+/// By default unknown identifiers are rejected while reserved `EventFlag` bits
+/// are kept, matching how the ANCS specification expects consumers to treat them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Keep identifiers this crate does not recognise in an `Unknown(u8)` variant
+    /// instead of failing, so newer Notification Providers keep working.
+    pub allow_unknown_ids: bool,
+    /// Reject `EventFlag` bits reserved by the ANCS specification instead of
+    /// keeping them.
+    pub reject_reserved_flags: bool,
+    /// Report input that ends early as `nom::Err::Incomplete` with the number of
+    /// bytes still expected, instead of failing, so callers can feed data in chunks.
+    pub streaming: bool,
+}
+
+impl ParseOptions {
+    /// Rejects anything not defined by the ANCS specification.
+    pub const fn strict() -> ParseOptions {
+        ParseOptions {
+            allow_unknown_ids: false,
+            reject_reserved_flags: true,
+            streaming: false,
+        }
+    }
+
+    /// Accepts identifiers added by future revisions of the ANCS specification.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::category::CategoryID;
+    /// let data: [u8; 1] = [42];
+    ///
+    /// assert!(CategoryID::parse(&data).is_err());
+    ///
+    /// let (_, category_id) = CategoryID::parse_with(&data, ParseOptions::lenient()).unwrap();
+    /// assert_eq!(CategoryID::Unknown(42), category_id);
+    /// assert_eq!(42_u8, category_id.into());
+    /// ```
+    pub const fn lenient() -> ParseOptions {
+        ParseOptions {
+            allow_unknown_ids: true,
+            reject_reserved_flags: false,
+            streaming: false,
+        }
+    }
+
+    /// Returns these options with streaming parsing enabled.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::ParseOptions;
+    /// # use ancs::attributes::NotificationAttribute;
+    /// let data: Vec<u8> = vec![1, 4, 0, 116];
+    /// let result = NotificationAttribute::parse_with(&data, ParseOptions::lenient().streaming());
+    ///
+    /// assert_eq!(result, Err(nom::Err::Incomplete(nom::Needed::new(3))));
+    /// ```
+    pub const fn streaming(self) -> ParseOptions {
+        ParseOptions {
+            streaming: true,
+            ..self
+        }
+    }
+}
+
+pub(crate) fn le_u8(i: &[u8], options: ParseOptions) -> IResult<&[u8], u8, Error> {
+    match options.streaming {
+        true => streaming::le_u8(i),
+        false => complete::le_u8(i),
+    }
+}
+
+pub(crate) fn le_u16(i: &[u8], options: ParseOptions) -> IResult<&[u8], u16, Error> {
+    match options.streaming {
+        true => streaming::le_u16(i),
+        false => complete::le_u16(i),
+    }
+}
+
+pub(crate) fn le_u32(i: &[u8], options: ParseOptions) -> IResult<&[u8], u32, Error> {
+    match options.streaming {
+        true => streaming::le_u32(i),
+        false => complete::le_u32(i),
+    }
+}
+
+pub(crate) fn take(i: &[u8], count: usize, options: ParseOptions) -> IResult<&[u8], &[u8], Error> {
+    match options.streaming {
+        true => streaming_bytes::take(count)(i),
+        false => complete_bytes::take(count)(i),
+    }
+}
+
+pub(crate) fn take_till_nul(i: &[u8], options: ParseOptions) -> IResult<&[u8], &[u8], Error> {
+    match options.streaming {
+        true => streaming_bytes::take_till(|b| b == 0)(i),
+        false => complete_bytes::take_till(|b| b == 0)(i),
+    }
+}
@@ -0,0 +1,498 @@
+//! ## Server
+//!
+//! The Notification Provider is the iOS side of ANCS: it owns the notifications,
+//! announces them on the Notification Source, and answers Control Point commands
+//! on the Data Source. The `AncsServer` simulates that role in memory, so that
+//! accessory firmware and host code can be exercised without an iPhone. Control
+//! Point writes are parsed with the same parsers a Notification Consumer would
+//! use to build them, requested maximum lengths are honored, bad commands are
+//! rejected with the `ControlPointError` iOS would answer with, and responses
+//! are split into notifications that fit the negotiated ATT MTU.
+//!
+//! The `scenario` submodule drives an `AncsServer` from a declarative timeline.
+//!
+pub mod scenario;
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::attributes::action::ActionID;
+use crate::attributes::app::AppAttributeID;
+use crate::attributes::category::CategoryID;
+use crate::attributes::command::CommandID;
+use crate::attributes::event::{EventFlag, EventID};
+use crate::attributes::notification::NotificationAttributeID;
+use crate::attributes::{AppAttribute, NotificationAttribute};
+use crate::characteristics::control_point::{
+    ControlPointError, ControlPointRequest, GetAppAttributesRequest, GetNotificationAttributesRequest,
+    PerformNotificationActionRequest,
+};
+use crate::characteristics::data_source::{
+    DataSourceFragmenter, DataSourceResponse, GetAppAttributesResponse, GetNotificationAttributesResponse,
+};
+use crate::characteristics::notification_source::Notification;
+use crate::{Error, ParseOptions};
+
+/// A notification held by an `AncsServer`, with the attribute values it answers
+/// Get Notification Attributes commands with.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::EventFlag;
+/// # use ancs::attributes::notification::NotificationAttributeID;
+/// # use ancs::server::ServerNotification;
+/// let notification = ServerNotification::new(CategoryID::Social)
+///     .with_flags(EventFlag::Important)
+///     .with_attribute(NotificationAttributeID::Title, "Ann".to_string());
+///
+/// assert_eq!(notification.attribute(NotificationAttributeID::Title), Some("Ann"));
+/// assert_eq!(notification.attribute(NotificationAttributeID::Message), None);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServerNotification {
+    pub category_id: CategoryID,
+    #[cfg_attr(feature = "serde", serde(default = "EventFlag::empty"))]
+    pub event_flags: EventFlag,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attributes: HashMap<NotificationAttributeID, String>,
+}
+
+impl ServerNotification {
+    /// Creates a `ServerNotification` in the given category, without flags or attributes.
+    pub fn new(category_id: CategoryID) -> ServerNotification {
+        ServerNotification {
+            category_id,
+            event_flags: EventFlag::empty(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Sets the `EventFlag`s announced with the notification.
+    pub fn with_flags(mut self, event_flags: EventFlag) -> ServerNotification {
+        self.event_flags = event_flags;
+        self
+    }
+
+    /// Sets the value of an attribute.
+    pub fn with_attribute(mut self, id: NotificationAttributeID, value: String) -> ServerNotification {
+        self.attributes.insert(id, value);
+        self
+    }
+
+    /// Returns the value of an attribute, if it was set.
+    pub fn attribute(&self, id: NotificationAttributeID) -> Option<&str> {
+        self.attributes.get(&id).map(String::as_str)
+    }
+
+    /// Returns the value answered for an attribute. Unset attributes are empty,
+    /// except `MessageSize`, which defaults to the length of the `Message`.
+    fn value(&self, id: NotificationAttributeID) -> String {
+        match (self.attribute(id), id) {
+            (Some(value), _) => value.to_string(),
+            (None, NotificationAttributeID::MessageSize) => self
+                .attribute(NotificationAttributeID::Message)
+                .map(|message| message.len().to_string())
+                .unwrap_or_default(),
+            (None, _) => String::new(),
+        }
+    }
+}
+
+/// The `AncsServer` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::EventID;
+/// # use ancs::attributes::notification::NotificationAttributeID;
+/// # use ancs::characteristics::control_point::{ControlPointError, GetNotificationAttributesRequest, RequestedAttribute};
+/// # use ancs::characteristics::data_source::GetNotificationAttributesResponse;
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::server::{AncsServer, ServerNotification};
+/// let mut server = AncsServer::new();
+/// let uid = server.add(
+///     ServerNotification::new(CategoryID::Social)
+///         .with_attribute(NotificationAttributeID::Title, "Hello there".to_string()),
+/// );
+///
+/// let bytes = server.poll_notification().unwrap();
+/// let (_, notification) = Notification::parse(&bytes).unwrap();
+/// assert_eq!((notification.event_id, notification.notification_uid), (EventID::NotificationAdded, uid));
+///
+/// let request: Vec<u8> = GetNotificationAttributesRequest {
+///     notification_uid: uid,
+///     attribute_ids: vec![RequestedAttribute::Title(5)],
+/// }.into();
+/// server.handle_control_point(&request).unwrap();
+///
+/// let bytes = server.poll_data_source().unwrap();
+/// let (_, response) = GetNotificationAttributesResponse::parse(&bytes).unwrap();
+/// assert_eq!(response.attribute_list[0].value.as_deref(), Some("Hello"));
+///
+/// // Unknown UIDs and malformed commands are rejected
+/// assert_eq!(server.handle_control_point(&[0, 99, 0, 0, 0, 1, 5, 0]), Err(ControlPointError::InvalidParameter));
+/// assert_eq!(server.handle_control_point(&[0, 1, 0]), Err(ControlPointError::InvalidCommand));
+/// assert_eq!(server.handle_control_point(&[9]), Err(ControlPointError::UnknownCommand));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct AncsServer {
+    next_uid: u32,
+    notifications: BTreeMap<u32, ServerNotification>,
+    /// Display names by app identifier.
+    apps: HashMap<String, String>,
+    fragmenter: DataSourceFragmenter,
+    notification_source: VecDeque<[u8; 8]>,
+    data_source: VecDeque<Vec<u8>>,
+    actions: VecDeque<PerformNotificationActionRequest>,
+    remove_on_negative: bool,
+}
+
+impl Default for AncsServer {
+    fn default() -> Self {
+        AncsServer::new()
+    }
+}
+
+impl AncsServer {
+    /// Creates an `AncsServer` without notifications or apps, sending Data
+    /// Source responses for the `DEFAULT_ATT_MTU`.
+    pub fn new() -> AncsServer {
+        AncsServer {
+            next_uid: 0,
+            notifications: BTreeMap::new(),
+            apps: HashMap::new(),
+            fragmenter: DataSourceFragmenter::default(),
+            notification_source: VecDeque::new(),
+            data_source: VecDeque::new(),
+            actions: VecDeque::new(),
+            remove_on_negative: false,
+        }
+    }
+
+    /// Sets the ATT MTU Data Source responses are fragmented for.
+    pub fn with_mtu(mut self, mtu: u16) -> AncsServer {
+        self.fragmenter = DataSourceFragmenter::new(mtu);
+        self
+    }
+
+    /// Returns the ATT MTU Data Source responses are fragmented for.
+    pub fn mtu(&self) -> u16 {
+        self.fragmenter.mtu()
+    }
+
+    /// Sets whether an accepted `ActionID::Negative` removes the notification,
+    /// as iOS does when a notification is dismissed. By default the server only
+    /// records actions for `poll_action`, leaving it to the caller to react.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::action::ActionID;
+    /// # use ancs::attributes::category::CategoryID;
+    /// # use ancs::attributes::event::{EventFlag, EventID};
+    /// # use ancs::characteristics::control_point::PerformNotificationActionRequest;
+    /// # use ancs::characteristics::notification_source::Notification;
+    /// # use ancs::server::{AncsServer, ServerNotification};
+    /// let mut server = AncsServer::new().with_remove_on_negative(true);
+    /// let uid = server.add(ServerNotification::new(CategoryID::Email).with_flags(EventFlag::NegativeAction));
+    /// server.poll_notification();
+    ///
+    /// let request: Vec<u8> = PerformNotificationActionRequest {
+    ///     notification_uid: uid,
+    ///     action_id: ActionID::Negative,
+    /// }.into();
+    /// server.handle_control_point(&request).unwrap();
+    ///
+    /// let (_, removed) = Notification::parse(&server.poll_notification().unwrap()).unwrap();
+    /// assert_eq!((removed.event_id, removed.notification_uid), (EventID::NotificationRemoved, uid));
+    /// assert_eq!(server.poll_action().map(|action| action.action_id), Some(ActionID::Negative));
+    /// assert_eq!(server.is_empty(), true);
+    /// ```
+    pub fn with_remove_on_negative(mut self, remove_on_negative: bool) -> AncsServer {
+        self.remove_on_negative = remove_on_negative;
+        self
+    }
+
+    /// Registers an app, answering Get App Attributes commands for it with its
+    /// display name. Apps that are not registered have an empty display name.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::attributes::app::AppAttributeID;
+    /// # use ancs::characteristics::control_point::GetAppAttributesRequest;
+    /// # use ancs::characteristics::data_source::GetAppAttributesResponse;
+    /// # use ancs::client::app_names::{AppName, AppNameCache};
+    /// # use ancs::server::AncsServer;
+    /// let mut server = AncsServer::new()
+    ///     .with_mtu(185)
+    ///     .with_app("com.apple.MobileSMS".to_string(), "Messages".to_string());
+    /// let mut names = AppNameCache::new();
+    ///
+    /// for app_identifier in ["com.apple.MobileSMS", "com.example.unknown"] {
+    ///     let request: Vec<u8> = GetAppAttributesRequest {
+    ///         app_identifier: app_identifier.to_string(),
+    ///         attribute_ids: vec![AppAttributeID::DisplayName],
+    ///     }.try_into().unwrap();
+    ///     server.handle_control_point(&request).unwrap();
+    ///
+    ///     let (_, response) = GetAppAttributesResponse::parse(&server.poll_data_source().unwrap()).unwrap();
+    ///     names.handle_response(&response);
+    /// }
+    ///
+    /// assert_eq!(names.get("com.apple.MobileSMS"), Some(&AppName::Known("Messages".to_string())));
+    /// assert_eq!(names.get("com.example.unknown"), Some(&AppName::Unknown));
+    /// ```
+    pub fn with_app(mut self, app_identifier: String, display_name: String) -> AncsServer {
+        self.add_app(app_identifier, display_name);
+        self
+    }
+
+    /// Registers an app, replacing its display name if it was already known.
+    pub fn add_app(&mut self, app_identifier: String, display_name: String) {
+        self.apps.insert(app_identifier, display_name);
+    }
+
+    /// Adds a notification under the next UID and announces it with
+    /// `EventID::NotificationAdded`. Returns the UID.
+    pub fn add(&mut self, notification: ServerNotification) -> u32 {
+        let uid = self.next_uid;
+        self.next_uid = self.next_uid.wrapping_add(1);
+
+        self.insert(uid, notification);
+        uid
+    }
+
+    /// Adds a notification under the given UID and announces it with
+    /// `EventID::NotificationAdded`, replacing any notification with that UID.
+    pub fn insert(&mut self, notification_uid: u32, notification: ServerNotification) {
+        self.next_uid = self.next_uid.max(notification_uid.wrapping_add(1));
+        self.notifications.insert(notification_uid, notification);
+        self.announce(EventID::NotificationAdded, notification_uid);
+    }
+
+    /// Replaces a notification and announces it with `EventID::NotificationModified`.
+    /// Returns `false` if there is no notification with the UID.
+    pub fn modify(&mut self, notification_uid: u32, notification: ServerNotification) -> bool {
+        match self.notifications.get_mut(&notification_uid) {
+            Some(stored) => {
+                *stored = notification;
+                self.announce(EventID::NotificationModified, notification_uid);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Removes a notification and announces it with `EventID::NotificationRemoved`.
+    /// Returns the notification, if there was one with the UID.
+    pub fn remove(&mut self, notification_uid: u32) -> Option<ServerNotification> {
+        let notification = self.notifications.remove(&notification_uid)?;
+
+        let event = Notification {
+            event_id: EventID::NotificationRemoved,
+            event_flags: notification.event_flags,
+            category_id: notification.category_id,
+            category_count: self.category_count(notification.category_id),
+            notification_uid,
+        };
+        self.notification_source.push_back(event.into());
+
+        Some(notification)
+    }
+
+    /// Handles a write to the Control Point. Responses are queued for
+    /// `poll_data_source` and performed actions for `poll_action`. Performing an
+    /// action leaves the notification in place unless `with_remove_on_negative`
+    /// is set.
+    ///
+    /// Commands with an unknown `CommandID` are rejected with `UnknownCommand`,
+    /// malformed ones with `InvalidCommand`, ones referring to an unknown
+    /// notification with `InvalidParameter`, and actions the notification does
+    /// not offer with `ActionFailed`. Like iOS, an app that was not registered is
+    /// answered with an empty display name rather than rejected.
+    pub fn handle_control_point(&mut self, bytes: &[u8]) -> Result<(), ControlPointError> {
+        let request = match ControlPointRequest::parse_with(bytes, ParseOptions::strict()) {
+            Ok(([], request)) => request,
+            Err(nom::Err::Failure(Error::InvalidCommandID(_))) => return Err(ControlPointError::UnknownCommand),
+            _ => return Err(ControlPointError::InvalidCommand),
+        };
+
+        match request {
+            ControlPointRequest::GetNotificationAttributes(request) => self.notification_attributes(request),
+            ControlPointRequest::GetAppAttributes(request) => self.app_attributes(request),
+            ControlPointRequest::PerformNotificationAction(request) => self.perform_action(request),
+        }
+    }
+
+    /// Returns the next Notification Source notification to send, if any.
+    pub fn poll_notification(&mut self) -> Option<[u8; 8]> {
+        self.notification_source.pop_front()
+    }
+
+    /// Returns the next Data Source notification to send, if any. Responses
+    /// longer than `MTU - 3` bytes are sent as several notifications.
+    pub fn poll_data_source(&mut self) -> Option<Vec<u8>> {
+        self.data_source.pop_front()
+    }
+
+    /// Returns the next action the Notification Consumer performed, if any.
+    pub fn poll_action(&mut self) -> Option<PerformNotificationActionRequest> {
+        self.actions.pop_front()
+    }
+
+    /// Returns the notification with the UID, if any.
+    pub fn get(&self, notification_uid: u32) -> Option<&ServerNotification> {
+        self.notifications.get(&notification_uid)
+    }
+
+    /// Returns the notifications ordered by UID.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &ServerNotification)> {
+        self.notifications.iter().map(|(uid, notification)| (*uid, notification))
+    }
+
+    /// Returns the number of notifications held.
+    pub fn len(&self) -> usize {
+        self.notifications.len()
+    }
+
+    /// Determines if no notifications are held.
+    pub fn is_empty(&self) -> bool {
+        self.notifications.is_empty()
+    }
+
+    /// Determines if there is nothing left to send on either characteristic.
+    pub fn is_idle(&self) -> bool {
+        self.notification_source.is_empty() && self.data_source.is_empty()
+    }
+
+    fn announce(&mut self, event_id: EventID, notification_uid: u32) {
+        let notification = &self.notifications[&notification_uid];
+
+        let event = Notification {
+            event_id,
+            event_flags: notification.event_flags,
+            category_id: notification.category_id,
+            category_count: self.category_count(notification.category_id),
+            notification_uid,
+        };
+        self.notification_source.push_back(event.into());
+    }
+
+    fn category_count(&self, category_id: CategoryID) -> u8 {
+        let count = self
+            .notifications
+            .values()
+            .filter(|notification| notification.category_id == category_id)
+            .count();
+
+        u8::try_from(count).unwrap_or(u8::MAX)
+    }
+
+    fn notification_attributes(&mut self, request: GetNotificationAttributesRequest) -> Result<(), ControlPointError> {
+        let notification = self
+            .notifications
+            .get(&request.notification_uid)
+            .ok_or(ControlPointError::InvalidParameter)?;
+
+        let attribute_list = request
+            .attribute_ids
+            .iter()
+            .map(|requested| {
+                let max_length = requested.max_length().unwrap_or(u16::MAX);
+                let value = truncate(notification.value(requested.id()), max_length.into());
+
+                NotificationAttribute {
+                    id: requested.id(),
+                    length: value.len() as u16,
+                    value: Some(value),
+                }
+            })
+            .collect();
+
+        let response = GetNotificationAttributesResponse {
+            command_id: CommandID::GetNotificationAttributes,
+            notification_uid: request.notification_uid,
+            attribute_list,
+        };
+
+        self.respond(response)
+    }
+
+    fn app_attributes(&mut self, request: GetAppAttributesRequest) -> Result<(), ControlPointError> {
+        let display_name = self.apps.get(&request.app_identifier).cloned().unwrap_or_default();
+
+        let attribute_list = request
+            .attribute_ids
+            .iter()
+            .map(|id| {
+                let value = match id {
+                    AppAttributeID::DisplayName => truncate(display_name.clone(), usize::from(u16::MAX)),
+                    AppAttributeID::Unknown(_) => String::new(),
+                };
+
+                AppAttribute {
+                    id: *id,
+                    length: value.len() as u16,
+                    value: Some(value),
+                }
+            })
+            .collect();
+
+        let response = GetAppAttributesResponse {
+            command_id: CommandID::GetAppAttributes,
+            app_identifier: request.app_identifier,
+            attribute_list,
+        };
+
+        self.respond(response)
+    }
+
+    fn perform_action(&mut self, request: PerformNotificationActionRequest) -> Result<(), ControlPointError> {
+        let notification = self
+            .notifications
+            .get(&request.notification_uid)
+            .ok_or(ControlPointError::InvalidParameter)?;
+
+        let flag = match request.action_id {
+            ActionID::Positive => EventFlag::PositiveAction,
+            ActionID::Negative => EventFlag::NegativeAction,
+            ActionID::Unknown(_) => return Err(ControlPointError::InvalidCommand),
+        };
+
+        if !notification.event_flags.contains(flag) {
+            return Err(ControlPointError::ActionFailed);
+        }
+
+        if self.remove_on_negative && request.action_id == ActionID::Negative {
+            self.remove(request.notification_uid);
+        }
+
+        self.actions.push_back(request);
+        Ok(())
+    }
+
+    fn respond(&mut self, response: impl Into<DataSourceResponse>) -> Result<(), ControlPointError> {
+        // Responses are built from validated values, so encoding can only fail
+        // for inputs the parsers already rejected.
+        let fragments = self
+            .fragmenter
+            .encode(response)
+            .map_err(|_| ControlPointError::InvalidCommand)?;
+
+        self.data_source.extend(fragments);
+        Ok(())
+    }
+}
+
+/// Truncates `value` to at most `max_length` bytes without splitting a character.
+fn truncate(mut value: String, max_length: usize) -> String {
+    if value.len() > max_length {
+        let mut end = max_length;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+    }
+
+    value
+}
@@ -0,0 +1,386 @@
+//! ## Scenario
+//!
+//! A `Scenario` scripts what a Notification Provider does over time: the apps
+//! it knows, and a timeline of notifications being added, modified and removed.
+//! Each step names the notification it concerns, so later steps can refer back
+//! to it, and can be repeated to describe bursts. A `ScenarioRunner` replays the
+//! timeline into an `AncsServer` on a virtual clock, so the same scenario always
+//! produces the same Notification Source events and Data Source answers.
+//!
+//! With the `serde` feature enabled a `Scenario` can be loaded from any format
+//! supported by `serde`, such as JSON or YAML, so regression scenarios can be
+//! written without code:
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! # use std::time::Duration;
+//! # use ancs::server::scenario::{Scenario, ScenarioRunner};
+//! let scenario: Scenario = serde_json::from_str(r#"{
+//!     "apps": { "com.apple.MobileSMS": "Messages" },
+//!     "timeline": [
+//!         {
+//!             "at_ms": 0,
+//!             "id": "call",
+//!             "event": { "Add": {
+//!                 "category_id": "IncomingCall",
+//!                 "event_flags": "PositiveAction | NegativeAction",
+//!                 "attributes": { "Title": "Ann" }
+//!             } }
+//!         },
+//!         {
+//!             "at_ms": 100,
+//!             "id": "message",
+//!             "repeat": { "count": 50, "interval_ms": 20 },
+//!             "event": { "Add": {
+//!                 "category_id": "Social",
+//!                 "attributes": { "AppIdentifier": "com.apple.MobileSMS", "Message": "Hi" }
+//!             } }
+//!         },
+//!         { "at_ms": 5000, "id": "call", "event": "Remove" }
+//!     ]
+//! }"#).unwrap();
+//!
+//! let mut runner = ScenarioRunner::new(scenario).unwrap();
+//!
+//! runner.advance_to(Duration::from_secs(2));
+//! assert_eq!(runner.server().len(), 51);
+//!
+//! runner.run_to_end();
+//! assert_eq!(runner.server().len(), 50);
+//! assert_eq!(runner.uid("message.49"), Some(50));
+//! # }
+//! ```
+//!
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use crate::server::{AncsServer, ServerNotification};
+
+/// What happens to a notification at a step of a `Scenario`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StepEvent {
+    /// The notification is added under a new UID.
+    Add(ServerNotification),
+    /// The notification is replaced.
+    Modify(ServerNotification),
+    /// The notification is removed.
+    Remove,
+}
+
+/// Repeats a `Step` `count` times, `interval_ms` apart. The `n`th repetition,
+/// counting from `0`, concerns the notification named `{id}.{n}`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Repeat {
+    pub count: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interval_ms: u64,
+}
+
+/// A `StepEvent` happening to the notification named `id`, `at_ms` milliseconds
+/// after the start of the `Scenario`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Step {
+    pub at_ms: u64,
+    pub id: String,
+    pub event: StepEvent,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub repeat: Option<Repeat>,
+}
+
+/// The `Scenario` type. See [the module level documentation](index.html) for more.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scenario {
+    /// Display names by app identifier.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub apps: BTreeMap<String, String>,
+    /// The steps, in any order. Steps at the same time happen in the order listed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timeline: Vec<Step>,
+}
+
+/// Why a `Scenario` cannot be run.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ScenarioError {
+    /// A notification is added while one with the same name is still present.
+    DuplicateNotification { id: String, at_ms: u64 },
+    /// A notification is modified or removed while none with that name is present.
+    UnknownNotification { id: String, at_ms: u64 },
+    /// A repetition of the step starting at `at_ms` falls after the last
+    /// representable millisecond.
+    TimeOverflow { id: String, at_ms: u64 },
+}
+
+impl std::fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::DuplicateNotification { id, at_ms } => {
+                write!(f, "notification {:?} added twice at {}ms", id, at_ms)
+            },
+            ScenarioError::UnknownNotification { id, at_ms } => {
+                write!(f, "notification {:?} does not exist at {}ms", id, at_ms)
+            },
+            ScenarioError::TimeOverflow { id, at_ms } => {
+                write!(f, "notification {:?} repeated from {}ms overflows the timeline", id, at_ms)
+            },
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+/// A single step after expanding repetitions.
+#[derive(Debug, PartialEq, Clone)]
+struct Scheduled {
+    at_ms: u64,
+    id: String,
+    event: StepEvent,
+}
+
+/// The `ScenarioRunner` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::EventID;
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::server::ServerNotification;
+/// # use ancs::server::scenario::{Repeat, Scenario, ScenarioError, ScenarioRunner, Step, StepEvent};
+/// let step = |at_ms, event| Step { at_ms, id: "mail".to_string(), event, repeat: None };
+/// let scenario = Scenario {
+///     timeline: vec![
+///         step(0, StepEvent::Add(ServerNotification::new(CategoryID::Email))),
+///         step(2000, StepEvent::Remove),
+///     ],
+///     ..Scenario::default()
+/// };
+///
+/// let mut runner = ScenarioRunner::new(scenario).unwrap();
+/// assert_eq!(runner.next_step_at(), Some(Duration::ZERO));
+///
+/// assert_eq!(runner.advance(Duration::from_secs(1)), 1);
+/// assert_eq!(runner.next_step_at(), Some(Duration::from_secs(2)));
+///
+/// runner.run_to_end();
+/// assert_eq!(runner.is_finished(), true);
+///
+/// let events: Vec<EventID> = std::iter::from_fn(|| runner.server_mut().poll_notification())
+///     .map(|bytes| Notification::parse(&bytes).unwrap().1.event_id)
+///     .collect();
+/// assert_eq!(events, vec![EventID::NotificationAdded, EventID::NotificationRemoved]);
+///
+/// // Steps must refer to notifications that exist at that time
+/// let scenario = Scenario { timeline: vec![step(0, StepEvent::Remove)], ..Scenario::default() };
+/// assert_eq!(
+///     ScenarioRunner::new(scenario),
+///     Err(ScenarioError::UnknownNotification { id: "mail".to_string(), at_ms: 0 })
+/// );
+///
+/// // and repetitions must not run past the end of time
+/// let scenario = Scenario {
+///     timeline: vec![Step {
+///         repeat: Some(Repeat { count: 2, interval_ms: 1000 }),
+///         ..step(u64::MAX - 500, StepEvent::Add(ServerNotification::new(CategoryID::Email)))
+///     }],
+///     ..Scenario::default()
+/// };
+/// assert_eq!(
+///     ScenarioRunner::new(scenario),
+///     Err(ScenarioError::TimeOverflow { id: "mail.1".to_string(), at_ms: u64::MAX - 500 })
+/// );
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScenarioRunner {
+    server: AncsServer,
+    /// Steps ordered by time.
+    steps: Vec<Scheduled>,
+    next: usize,
+    elapsed: Duration,
+    uids: HashMap<String, u32>,
+}
+
+impl ScenarioRunner {
+    /// Creates a `ScenarioRunner` replaying `scenario` into a new `AncsServer`.
+    pub fn new(scenario: Scenario) -> Result<ScenarioRunner, ScenarioError> {
+        ScenarioRunner::with_server(scenario, AncsServer::new())
+    }
+
+    /// Creates a `ScenarioRunner` replaying `scenario` into an existing `AncsServer`.
+    pub fn with_server(scenario: Scenario, mut server: AncsServer) -> Result<ScenarioRunner, ScenarioError> {
+        for (app_identifier, display_name) in scenario.apps {
+            server.add_app(app_identifier, display_name);
+        }
+
+        let mut steps: Vec<Scheduled> = Vec::new();
+        for step in scenario.timeline {
+            steps.extend(expand(step)?);
+        }
+        steps.sort_by_key(|step| step.at_ms);
+
+        validate(&steps)?;
+
+        Ok(ScenarioRunner {
+            server,
+            steps,
+            next: 0,
+            elapsed: Duration::ZERO,
+            uids: HashMap::new(),
+        })
+    }
+
+    /// Moves the virtual clock forward by `duration`, applying every step that
+    /// became due. Returns the number of steps applied.
+    pub fn advance(&mut self, duration: Duration) -> usize {
+        self.advance_to(self.elapsed + duration)
+    }
+
+    /// Moves the virtual clock to `elapsed` since the start of the scenario,
+    /// applying every step that became due. The clock never moves backwards.
+    /// Returns the number of steps applied.
+    pub fn advance_to(&mut self, elapsed: Duration) -> usize {
+        self.elapsed = self.elapsed.max(elapsed);
+
+        let start = self.next;
+        while let Some(step) = self.steps.get(self.next) {
+            if Duration::from_millis(step.at_ms) > self.elapsed {
+                break;
+            }
+
+            let step = step.clone();
+            self.apply(step);
+            self.next += 1;
+        }
+
+        self.next - start
+    }
+
+    /// Applies every remaining step, moving the virtual clock to the last one.
+    pub fn run_to_end(&mut self) -> usize {
+        match self.steps.last() {
+            Some(step) => self.advance_to(Duration::from_millis(step.at_ms)),
+            None => 0,
+        }
+    }
+
+    /// Returns when the next step is due, relative to the start of the scenario.
+    pub fn next_step_at(&self) -> Option<Duration> {
+        self.steps.get(self.next).map(|step| Duration::from_millis(step.at_ms))
+    }
+
+    /// Returns the virtual time elapsed since the start of the scenario.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Determines if every step has been applied.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.steps.len()
+    }
+
+    /// Returns the UID the notification with the given name was last added under.
+    pub fn uid(&self, id: &str) -> Option<u32> {
+        self.uids.get(id).copied()
+    }
+
+    /// Returns the server the scenario is replayed into.
+    pub fn server(&self) -> &AncsServer {
+        &self.server
+    }
+
+    /// Returns the server the scenario is replayed into, for polling its
+    /// notifications and answering Control Point writes.
+    pub fn server_mut(&mut self) -> &mut AncsServer {
+        &mut self.server
+    }
+
+    /// Returns the server, abandoning the remaining steps.
+    pub fn into_server(self) -> AncsServer {
+        self.server
+    }
+
+    fn apply(&mut self, step: Scheduled) {
+        // `validate` guarantees that modified and removed notifications exist.
+        match step.event {
+            StepEvent::Add(notification) => {
+                let uid = self.server.add(notification);
+                self.uids.insert(step.id, uid);
+            },
+            StepEvent::Modify(notification) => {
+                if let Some(uid) = self.uids.get(&step.id) {
+                    self.server.modify(*uid, notification);
+                }
+            },
+            StepEvent::Remove => {
+                if let Some(uid) = self.uids.get(&step.id) {
+                    self.server.remove(*uid);
+                }
+            },
+        }
+    }
+}
+
+/// Expands a `Step` into its repetitions.
+fn expand(step: Step) -> Result<Vec<Scheduled>, ScenarioError> {
+    match step.repeat {
+        Some(repeat) => (0..repeat.count)
+            .map(|n| {
+                let id = format!("{}.{}", step.id, n);
+                let at_ms = u64::from(n)
+                    .checked_mul(repeat.interval_ms)
+                    .and_then(|offset| step.at_ms.checked_add(offset));
+
+                match at_ms {
+                    Some(at_ms) => Ok(Scheduled {
+                        at_ms,
+                        id,
+                        event: step.event.clone(),
+                    }),
+                    None => Err(ScenarioError::TimeOverflow { id, at_ms: step.at_ms }),
+                }
+            })
+            .collect(),
+        None => Ok(vec![Scheduled {
+            at_ms: step.at_ms,
+            id: step.id,
+            event: step.event,
+        }]),
+    }
+}
+
+/// Checks that every step refers to a notification that exists at that time.
+fn validate(steps: &[Scheduled]) -> Result<(), ScenarioError> {
+    let mut present: HashSet<&str> = HashSet::new();
+
+    for step in steps {
+        let exists = present.contains(step.id.as_str());
+
+        match (&step.event, exists) {
+            (StepEvent::Add(_), true) => {
+                return Err(ScenarioError::DuplicateNotification {
+                    id: step.id.clone(),
+                    at_ms: step.at_ms,
+                })
+            },
+            (StepEvent::Modify(_) | StepEvent::Remove, false) => {
+                return Err(ScenarioError::UnknownNotification {
+                    id: step.id.clone(),
+                    at_ms: step.at_ms,
+                })
+            },
+            (StepEvent::Add(_), false) => {
+                present.insert(&step.id);
+            },
+            (StepEvent::Remove, true) => {
+                present.remove(step.id.as_str());
+            },
+            (StepEvent::Modify(_), true) => (),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! ## Transport
+//!
+//! ANCS is carried over three GATT characteristics: the Control Point, which is
+//! written to with response, and the Notification Source and Data Source, which
+//! notify. The `GattTransport` trait abstracts these operations over whichever
+//! Bluetooth stack is in use, so that an `AncsClient` can be driven with `drive`
+//! against real hardware or, in tests, against a `mock::MockTransport`. Any
+//! transport can be wrapped in a `fault::FaultInjector` to exercise how the
+//! client copes with lost, reordered or damaged traffic, and `loopback` connects
+//! a client to a simulated Notification Provider for end-to-end tests.
+//!
+pub mod fault;
+pub mod loopback;
+pub mod mock;
+
+use uuid::Uuid;
+
+use crate::characteristics::control_point::WriteError;
+use crate::client::clock::Clock;
+use crate::client::{AncsClient, SUBSCRIPTIONS};
+use crate::Error;
+
+/// The GATT operations performed on the ANCS characteristics.
+pub trait GattTransport {
+    /// The error reported by the Bluetooth stack.
+    type Error;
+
+    /// Writes to the Control Point with response, reporting a `ControlPointError`
+    /// if the Notification Provider rejects the write.
+    fn write_control_point(&mut self, bytes: &[u8]) -> Result<(), WriteError<Self::Error>>;
+
+    /// Enables notifications on one of the ANCS characteristics.
+    fn enable_notifications(&mut self, characteristic: Uuid) -> Result<(), Self::Error>;
+
+    /// Returns the next notification received on the Notification Source or
+    /// Data Source, or `None` if none is pending. Never blocks.
+    fn receive(&mut self) -> Result<Option<(Uuid, Vec<u8>)>, Self::Error>;
+}
+
+/// Why `drive` stopped.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TransportError<E> {
+    /// The transport failed.
+    Transport(E),
+    /// Bytes received from the Notification Provider could not be handled.
+    Protocol(Error),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for TransportError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportError::Transport(error) => write!(f, "transport error: {}", error),
+            TransportError::Protocol(error) => write!(f, "protocol error: {}", error),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for TransportError<E> {}
+
+/// Enables notifications on the characteristics in `SUBSCRIPTIONS`, in order.
+pub fn subscribe<T: GattTransport>(transport: &mut T) -> Result<(), T::Error> {
+    for characteristic in SUBSCRIPTIONS {
+        transport.enable_notifications(characteristic)?;
+    }
+
+    Ok(())
+}
+
+/// Exchanges everything pending between a client and a transport: hands every
+/// Control Point write to the transport and feeds every received notification
+/// to the client, until neither has anything left. Commands whose deadline has
+/// passed are timed out along the way.
+///
+/// # Examples
+/// ```
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::{EventFlag, EventID};
+/// # use ancs::characteristics::control_point::RequestedAttribute;
+/// # use ancs::characteristics::notification_source::Notification;
+/// # use ancs::client::{AncsClient, Event};
+/// # use ancs::transport::{drive, subscribe};
+/// # use ancs::transport::mock::MockTransport;
+/// let mut client = AncsClient::new().with_attributes(vec![RequestedAttribute::Title(16)]);
+/// let mut transport = MockTransport::new();
+///
+/// subscribe(&mut transport).unwrap();
+/// transport.inject_notification(Notification {
+///     event_id: EventID::NotificationAdded,
+///     event_flags: EventFlag::empty(),
+///     category_id: CategoryID::Social,
+///     category_count: 1,
+///     notification_uid: 7,
+/// });
+/// transport.respond_to(vec![0, 7, 0, 0, 0, 1, 16, 0], vec![vec![0, 7, 0, 0, 0, 1, 2, 0, 72, 105]]);
+///
+/// drive(&mut client, &mut transport).unwrap();
+///
+/// assert_eq!(transport.writes(), &[vec![0, 7, 0, 0, 0, 1, 16, 0]]);
+/// assert!(matches!(client.poll_event(), Some(Event::NotificationAdded { .. })));
+/// ```
+pub fn drive<T, C>(client: &mut AncsClient<C>, transport: &mut T) -> Result<(), TransportError<T::Error>>
+where
+    T: GattTransport,
+    C: Clock,
+{
+    loop {
+        if let Some(bytes) = client.poll_write() {
+            client
+                .handle_write(transport.write_control_point(&bytes))
+                .map_err(TransportError::Transport)?;
+            continue;
+        }
+
+        match transport.receive().map_err(TransportError::Transport)? {
+            Some((characteristic, bytes)) => client
+                .handle_notification(characteristic, &bytes)
+                .map_err(TransportError::Protocol)?,
+            None => return Ok(()),
+        }
+    }
+}
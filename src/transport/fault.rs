@@ -0,0 +1,452 @@
+//! ## Fault Injection
+//!
+//! Clients that work against a well-behaved Notification Provider still break
+//! in the field on truncated Data Source fragments, a Removed event overtaking
+//! its Added event, duplicated UIDs or a `ControlPointError` on a valid request.
+//! The `FaultInjector` wraps any `GattTransport` and injects such faults into
+//! the Notification Source and Data Source traffic it receives and the Control
+//! Point writes it sends, including over-long notifications and attribute
+//! values that are not valid UTF-8. Every fault is decided by a `SplitMix64`
+//! generator seeded by the caller, so a failing run reproduces exactly from its
+//! seed, and every injected fault is recorded for diagnostics.
+//!
+use std::collections::VecDeque;
+
+use uuid::Uuid;
+
+use crate::characteristics::control_point::{ControlPointError, WriteError};
+use crate::characteristics::data_source::DATA_SOURCE_UUID;
+use crate::characteristics::notification_source::NOTIFICATION_SOURCE_UUID;
+use crate::transport::GattTransport;
+
+/// A fault injected by a `FaultInjector`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Fault {
+    /// A notification was never delivered.
+    Dropped(Uuid),
+    /// A notification was delivered twice in a row.
+    Duplicated(Uuid),
+    /// A notification was delivered after the one following it.
+    Reordered(Uuid),
+    /// A notification was held back for this many calls to `receive`.
+    Delayed { characteristic: Uuid, receives: u32 },
+    /// A notification was cut short.
+    Truncated { characteristic: Uuid, from: usize, to: usize },
+    /// Random bytes were appended to a notification.
+    Extended { characteristic: Uuid, from: usize, to: usize },
+    /// A bit of a notification was flipped.
+    Corrupted { characteristic: Uuid, index: usize, bit: u8 },
+    /// A byte of a notification was replaced with `0xFF`, which never occurs in UTF-8.
+    InvalidUtf8 { characteristic: Uuid, index: usize },
+    /// A Control Point write was rejected without reaching the transport.
+    Rejected(ControlPointError),
+}
+
+/// The SplitMix64 pseudo random generator, small and fully determined by its seed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns `true` with the given probability. Probabilities of zero and
+    /// below never draw a number, so disabled faults do not shift the sequence.
+    fn chance(&mut self, probability: f64) -> bool {
+        if probability <= 0.0 {
+            return false;
+        }
+
+        // The top 53 bits give a uniformly distributed `f64` in `[0, 1)`.
+        let sample = (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+        sample < probability
+    }
+
+    /// Returns a number in `0..bound`, which must not be zero.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// The probability of each fault, all zero by default.
+#[derive(Debug, PartialEq, Clone)]
+struct Probabilities {
+    drop: f64,
+    duplicate: f64,
+    reorder: f64,
+    delay: f64,
+    truncate: f64,
+    extend: f64,
+    corrupt: f64,
+    invalid_utf8: f64,
+    reject: f64,
+}
+
+/// The `FaultInjector` type. See [the module level documentation](index.html) for more.
+///
+/// Faults are applied to each received notification in a fixed order: it may be
+/// dropped or delayed, then truncated, extended, corrupted and made invalid
+/// UTF-8, then duplicated, and finally swapped with the notification after it,
+/// which goes through the same faults first and is skipped if it is dropped or
+/// delayed. Delayed notifications are released once the given number of calls
+/// to `receive` went by, including calls that returned `None`, so they can
+/// overtake the end of a `drive`.
+///
+/// # Examples
+/// ```
+/// # use ancs::characteristics::control_point::{ControlPointError, WriteError};
+/// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+/// # use ancs::transport::GattTransport;
+/// # use ancs::transport::fault::{Fault, FaultInjector};
+/// # use ancs::transport::mock::MockTransport;
+/// let run = |seed| {
+///     let mut mock = MockTransport::new();
+///     mock.enable_notifications(DATA_SOURCE_UUID).unwrap();
+///     for i in 0..20 {
+///         mock.inject_data_source(vec![i; 4]);
+///     }
+///
+///     let mut transport = FaultInjector::new(mock, seed)
+///         .with_drop(0.2)
+///         .with_truncate(0.2)
+///         .with_reject(0.5, ControlPointError::InvalidParameter);
+///
+///     let received: Vec<Vec<u8>> = std::iter::from_fn(|| transport.receive().unwrap())
+///         .map(|(_, bytes)| bytes)
+///         .collect();
+///     (received, transport.faults().to_vec())
+/// };
+///
+/// // The same seed injects the same faults
+/// let (received, faults) = run(7);
+/// assert_eq!(run(7), (received.clone(), faults.clone()));
+/// assert!(received.len() < 20);
+/// assert!(faults.iter().any(|fault| matches!(fault, Fault::Truncated { .. })));
+///
+/// // Every write is rejected with a probability of one
+/// let mut transport = FaultInjector::new(MockTransport::new(), 1)
+///     .with_reject(1.0, ControlPointError::InvalidParameter);
+/// assert_eq!(
+///     transport.write_control_point(&[2, 0, 0, 0, 0, 0]),
+///     Err(WriteError::Rejected(ControlPointError::InvalidParameter))
+/// );
+/// assert_eq!(transport.inner().writes().len(), 0);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct FaultInjector<T> {
+    inner: T,
+    rng: SplitMix64,
+    probabilities: Probabilities,
+    max_delay: u32,
+    max_extension: usize,
+    reject_with: ControlPointError,
+    targets: Vec<Uuid>,
+    /// Notifications delivered before anything else is received.
+    ready: VecDeque<(Uuid, Vec<u8>)>,
+    /// Delayed notifications and the calls to `receive` left until their release.
+    delayed: Vec<(u32, Uuid, Vec<u8>)>,
+    faults: Vec<Fault>,
+}
+
+impl<T: GattTransport> FaultInjector<T> {
+    /// Creates a `FaultInjector` around `inner` that injects no faults yet,
+    /// drawing every decision from a generator seeded with `seed`.
+    pub fn new(inner: T, seed: u64) -> FaultInjector<T> {
+        FaultInjector {
+            inner,
+            rng: SplitMix64 { state: seed },
+            probabilities: Probabilities {
+                drop: 0.0,
+                duplicate: 0.0,
+                reorder: 0.0,
+                delay: 0.0,
+                truncate: 0.0,
+                extend: 0.0,
+                corrupt: 0.0,
+                invalid_utf8: 0.0,
+                reject: 0.0,
+            },
+            max_delay: 1,
+            max_extension: 1,
+            reject_with: ControlPointError::InvalidParameter,
+            targets: vec![NOTIFICATION_SOURCE_UUID, DATA_SOURCE_UUID],
+            ready: VecDeque::new(),
+            delayed: Vec::new(),
+            faults: Vec::new(),
+        }
+    }
+
+    /// Sets the probability that a notification is dropped.
+    pub fn with_drop(mut self, probability: f64) -> FaultInjector<T> {
+        self.probabilities.drop = probability;
+        self
+    }
+
+    /// Sets the probability that a notification is delivered twice.
+    pub fn with_duplicate(mut self, probability: f64) -> FaultInjector<T> {
+        self.probabilities.duplicate = probability;
+        self
+    }
+
+    /// Sets the probability that a notification is swapped with the next one.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+    /// # use ancs::transport::GattTransport;
+    /// # use ancs::transport::fault::{Fault, FaultInjector};
+    /// # use ancs::transport::mock::MockTransport;
+    /// let mut mock = MockTransport::new();
+    /// mock.enable_notifications(DATA_SOURCE_UUID).unwrap();
+    /// for i in 0..3 {
+    ///     mock.inject_data_source(vec![i]);
+    /// }
+    ///
+    /// let mut transport = FaultInjector::new(mock, 3).with_reorder(1.0);
+    /// let received: Vec<Vec<u8>> = std::iter::from_fn(|| transport.receive().unwrap())
+    ///     .map(|(_, bytes)| bytes)
+    ///     .collect();
+    ///
+    /// // The last notification has nothing to swap with
+    /// assert_eq!(received, vec![vec![1], vec![0], vec![2]]);
+    /// assert_eq!(transport.faults(), &[Fault::Reordered(DATA_SOURCE_UUID)]);
+    /// ```
+    pub fn with_reorder(mut self, probability: f64) -> FaultInjector<T> {
+        self.probabilities.reorder = probability;
+        self
+    }
+
+    /// Sets the probability that a notification is held back, and for at most
+    /// how many calls to `receive`.
+    pub fn with_delay(mut self, probability: f64, max_receives: u32) -> FaultInjector<T> {
+        self.probabilities.delay = probability;
+        self.max_delay = max_receives.max(1);
+        self
+    }
+
+    /// Sets the probability that a notification is cut to a random shorter length.
+    pub fn with_truncate(mut self, probability: f64) -> FaultInjector<T> {
+        self.probabilities.truncate = probability;
+        self
+    }
+
+    /// Sets the probability that random bytes are appended to a notification,
+    /// and at most how many, for example to exceed the ATT MTU.
+    pub fn with_extend(mut self, probability: f64, max_bytes: usize) -> FaultInjector<T> {
+        self.probabilities.extend = probability;
+        self.max_extension = max_bytes.max(1);
+        self
+    }
+
+    /// Sets the probability that a random bit of a notification is flipped.
+    pub fn with_corrupt(mut self, probability: f64) -> FaultInjector<T> {
+        self.probabilities.corrupt = probability;
+        self
+    }
+
+    /// Sets the probability that a random byte of a notification is replaced
+    /// with `0xFF`, so that an attribute value it lands in is not valid UTF-8.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+    /// # use ancs::transport::GattTransport;
+    /// # use ancs::transport::fault::{Fault, FaultInjector};
+    /// # use ancs::transport::mock::MockTransport;
+    /// let mut mock = MockTransport::new();
+    /// mock.enable_notifications(DATA_SOURCE_UUID).unwrap();
+    /// mock.inject_data_source(b"Messages".to_vec());
+    ///
+    /// let mut transport = FaultInjector::new(mock, 5).with_invalid_utf8(1.0);
+    /// let (_, bytes) = transport.receive().unwrap().unwrap();
+    ///
+    /// assert!(String::from_utf8(bytes).is_err());
+    /// assert!(matches!(transport.faults(), [Fault::InvalidUtf8 { .. }]));
+    /// ```
+    pub fn with_invalid_utf8(mut self, probability: f64) -> FaultInjector<T> {
+        self.probabilities.invalid_utf8 = probability;
+        self
+    }
+
+    /// Sets the probability that a Control Point write is rejected with `error`
+    /// instead of being passed to the transport.
+    pub fn with_reject(mut self, probability: f64, error: ControlPointError) -> FaultInjector<T> {
+        self.probabilities.reject = probability;
+        self.reject_with = error;
+        self
+    }
+
+    /// Restricts faults to notifications on the given characteristics, both the
+    /// Notification Source and the Data Source by default.
+    pub fn with_targets(mut self, characteristics: Vec<Uuid>) -> FaultInjector<T> {
+        self.targets = characteristics;
+        self
+    }
+
+    /// Returns every fault injected so far, in order.
+    pub fn faults(&self) -> &[Fault] {
+        &self.faults
+    }
+
+    /// Returns the number of notifications held back for later delivery.
+    pub fn held(&self) -> usize {
+        self.ready.len() + self.delayed.len()
+    }
+
+    /// Returns the wrapped transport.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the wrapped transport, for example to inject notifications into a mock.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped transport, dropping any held back notifications.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Counts down delayed notifications, moving those that are due to `ready`.
+    fn release_delayed(&mut self) {
+        let mut index = 0;
+        while index < self.delayed.len() {
+            let (receives, _, _) = &mut self.delayed[index];
+            *receives -= 1;
+
+            if *receives == 0 {
+                let (_, characteristic, bytes) = self.delayed.remove(index);
+                self.ready.push_back((characteristic, bytes));
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Applies every fault but reordering to a received notification, returning
+    /// the copies to deliver, or `None` if it was dropped or delayed.
+    /// Notifications on characteristics that are not targeted pass unchanged.
+    fn process(&mut self, characteristic: Uuid, mut bytes: Vec<u8>) -> Option<Vec<(Uuid, Vec<u8>)>> {
+        if !self.targets.contains(&characteristic) {
+            return Some(vec![(characteristic, bytes)]);
+        }
+
+        if self.rng.chance(self.probabilities.drop) {
+            self.faults.push(Fault::Dropped(characteristic));
+            return None;
+        }
+
+        if self.rng.chance(self.probabilities.delay) {
+            let receives = 1 + self.rng.below(self.max_delay as usize) as u32;
+
+            self.delayed.push((receives, characteristic, bytes));
+            self.faults.push(Fault::Delayed { characteristic, receives });
+            return None;
+        }
+
+        self.damage(characteristic, &mut bytes);
+
+        if self.rng.chance(self.probabilities.duplicate) {
+            self.faults.push(Fault::Duplicated(characteristic));
+            return Some(vec![(characteristic, bytes.clone()), (characteristic, bytes)]);
+        }
+
+        Some(vec![(characteristic, bytes)])
+    }
+
+    /// Truncates, extends and corrupts a notification.
+    fn damage(&mut self, characteristic: Uuid, bytes: &mut Vec<u8>) {
+        if !bytes.is_empty() && self.rng.chance(self.probabilities.truncate) {
+            let from = bytes.len();
+            let to = self.rng.below(from);
+
+            bytes.truncate(to);
+            self.faults.push(Fault::Truncated { characteristic, from, to });
+        }
+
+        if self.rng.chance(self.probabilities.extend) {
+            let from = bytes.len();
+            let to = from + 1 + self.rng.below(self.max_extension);
+
+            while bytes.len() < to {
+                bytes.push(self.rng.next_u64() as u8);
+            }
+            self.faults.push(Fault::Extended { characteristic, from, to });
+        }
+
+        if !bytes.is_empty() && self.rng.chance(self.probabilities.corrupt) {
+            let index = self.rng.below(bytes.len());
+            let bit = self.rng.below(8) as u8;
+
+            bytes[index] ^= 1 << bit;
+            self.faults.push(Fault::Corrupted { characteristic, index, bit });
+        }
+
+        if !bytes.is_empty() && self.rng.chance(self.probabilities.invalid_utf8) {
+            let index = self.rng.below(bytes.len());
+
+            bytes[index] = 0xFF;
+            self.faults.push(Fault::InvalidUtf8 { characteristic, index });
+        }
+    }
+}
+
+impl<T: GattTransport> GattTransport for FaultInjector<T> {
+    type Error = T::Error;
+
+    fn write_control_point(&mut self, bytes: &[u8]) -> Result<(), WriteError<T::Error>> {
+        if self.rng.chance(self.probabilities.reject) {
+            self.faults.push(Fault::Rejected(self.reject_with));
+            return Err(WriteError::Rejected(self.reject_with));
+        }
+
+        self.inner.write_control_point(bytes)
+    }
+
+    fn enable_notifications(&mut self, characteristic: Uuid) -> Result<(), T::Error> {
+        self.inner.enable_notifications(characteristic)
+    }
+
+    fn receive(&mut self) -> Result<Option<(Uuid, Vec<u8>)>, T::Error> {
+        self.release_delayed();
+
+        if let Some(notification) = self.ready.pop_front() {
+            return Ok(Some(notification));
+        }
+
+        while let Some((characteristic, bytes)) = self.inner.receive()? {
+            let mut copies = match self.process(characteristic, bytes) {
+                Some(copies) => copies,
+                None => continue,
+            };
+
+            if self.targets.contains(&characteristic) && self.rng.chance(self.probabilities.reorder) {
+                // The following notification goes through the same faults, and
+                // the swap only happens if it survives them.
+                while let Some((next, bytes)) = self.inner.receive()? {
+                    if let Some(next_copies) = self.process(next, bytes) {
+                        self.faults.push(Fault::Reordered(characteristic));
+                        copies = next_copies.into_iter().chain(copies).collect();
+                        break;
+                    }
+                }
+            }
+
+            let mut copies = copies.into_iter();
+            let first = copies.next();
+            self.ready.extend(copies);
+            return Ok(first);
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,451 @@
+//! ## Loopback
+//!
+//! End-to-end tests need both ends of ANCS without a radio in between. The
+//! `LoopbackTransport` is a `GattTransport` connected straight to an
+//! `AncsServer`: Control Point writes are answered by the server, and its
+//! Notification Source and Data Source notifications arrive after a fixed
+//! latency, fragmented for the configured ATT MTU. Time is a `ManualClock`.
+//! Since `GattTransport::write_control_point` reports its result as it returns,
+//! a write is acknowledged at once, but the responses it triggers arrive a full
+//! round trip later.
+//!
+//! The `Loopback` harness wires an `AncsClient` and a `NotificationStore` to
+//! such a transport and runs the whole subscribe, notify, fetch and act cycle
+//! on the virtual clock, jumping straight to the next delivery or timeout, so
+//! tests of seconds of traffic run in milliseconds. Once idle, its assertions
+//! check the notification state the client ended up with.
+//!
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::attributes::notification::NotificationAttributeID;
+use crate::characteristics::control_point::WriteError;
+use crate::characteristics::data_source::DATA_SOURCE_UUID;
+use crate::characteristics::notification_source::NOTIFICATION_SOURCE_UUID;
+use crate::client::clock::{Clock, ManualClock};
+use crate::client::store::{NotificationStore, TrackedNotification};
+use crate::client::{AncsClient, Event};
+use crate::server::AncsServer;
+use crate::transport::{self, GattTransport, TransportError};
+use crate::Error;
+
+/// The `LoopbackTransport` type. See [the module level documentation](index.html) for more.
+///
+/// Notifications are only sent on characteristics with notifications enabled,
+/// as on real hardware, and are delivered in the order the server sent them.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::characteristics::notification_source::NOTIFICATION_SOURCE_UUID;
+/// # use ancs::client::clock::ManualClock;
+/// # use ancs::server::{AncsServer, ServerNotification};
+/// # use ancs::transport::{subscribe, GattTransport};
+/// # use ancs::transport::loopback::LoopbackTransport;
+/// let clock = ManualClock::new();
+/// let mut transport = LoopbackTransport::new(AncsServer::new(), clock.clone())
+///     .with_latency(Duration::from_millis(30));
+/// subscribe(&mut transport).unwrap();
+///
+/// transport.server_mut().add(ServerNotification::new(CategoryID::Email));
+/// assert_eq!(transport.receive(), Ok(None));
+///
+/// clock.advance(Duration::from_millis(30));
+/// let (characteristic, bytes) = transport.receive().unwrap().unwrap();
+/// assert_eq!((characteristic, bytes.len()), (NOTIFICATION_SOURCE_UUID, 8));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoopbackTransport {
+    server: AncsServer,
+    clock: ManualClock,
+    latency: Duration,
+    enabled: Vec<Uuid>,
+    /// Sent notifications and when they are delivered, in the order they were sent.
+    in_flight: VecDeque<(Instant, Uuid, Vec<u8>)>,
+}
+
+impl LoopbackTransport {
+    /// Creates a `LoopbackTransport` connected to `server`, delivering without
+    /// latency and keeping time with `clock`.
+    pub fn new(server: AncsServer, clock: ManualClock) -> LoopbackTransport {
+        LoopbackTransport {
+            server,
+            clock,
+            latency: Duration::ZERO,
+            enabled: Vec::new(),
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    /// Sets how long notifications take to arrive, and Control Point writes
+    /// take to reach the server.
+    pub fn with_latency(mut self, latency: Duration) -> LoopbackTransport {
+        self.latency = latency;
+        self
+    }
+
+    /// Returns the server at the other end.
+    pub fn server(&self) -> &AncsServer {
+        &self.server
+    }
+
+    /// Returns the server at the other end, for example to add notifications.
+    pub fn server_mut(&mut self) -> &mut AncsServer {
+        &mut self.server
+    }
+
+    /// Returns when the next notification in flight arrives, if any.
+    pub fn next_delivery(&self) -> Option<Instant> {
+        self.in_flight.front().map(|(at, _, _)| *at)
+    }
+
+    /// Determines if notifications are in flight or waiting to be sent.
+    pub fn is_idle(&self) -> bool {
+        self.in_flight.is_empty() && self.server.is_idle()
+    }
+
+    /// Sends everything the server has queued on characteristics with
+    /// notifications enabled, dropping the rest.
+    fn send(&mut self) {
+        self.send_after(self.latency);
+    }
+
+    /// Sends like `send`, delivering after `delay` instead of the latency.
+    fn send_after(&mut self, delay: Duration) {
+        let at = self.clock.now() + delay;
+
+        while let Some(bytes) = self.server.poll_notification() {
+            if self.enabled.contains(&NOTIFICATION_SOURCE_UUID) {
+                self.in_flight.push_back((at, NOTIFICATION_SOURCE_UUID, bytes.to_vec()));
+            }
+        }
+
+        while let Some(bytes) = self.server.poll_data_source() {
+            if self.enabled.contains(&DATA_SOURCE_UUID) {
+                self.in_flight.push_back((at, DATA_SOURCE_UUID, bytes));
+            }
+        }
+    }
+}
+
+impl GattTransport for LoopbackTransport {
+    type Error = Infallible;
+
+    fn write_control_point(&mut self, bytes: &[u8]) -> Result<(), WriteError<Infallible>> {
+        // Notifications already queued went out before the write was answered,
+        // and the responses to it only leave once the write reached the server.
+        self.send();
+        self.server.handle_control_point(bytes)?;
+        self.send_after(self.latency * 2);
+
+        Ok(())
+    }
+
+    fn enable_notifications(&mut self, characteristic: Uuid) -> Result<(), Infallible> {
+        if !self.enabled.contains(&characteristic) {
+            self.enabled.push(characteristic);
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<(Uuid, Vec<u8>)>, Infallible> {
+        self.send();
+
+        match self.in_flight.front() {
+            Some((at, _, _)) if *at <= self.clock.now() => {
+                Ok(self.in_flight.pop_front().map(|(_, characteristic, bytes)| (characteristic, bytes)))
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The `Loopback` type. See [the module level documentation](index.html) for more.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use ancs::attributes::action::ActionID;
+/// # use ancs::attributes::category::CategoryID;
+/// # use ancs::attributes::event::EventFlag;
+/// # use ancs::attributes::notification::NotificationAttributeID;
+/// # use ancs::characteristics::control_point::RequestedAttribute;
+/// # use ancs::server::{AncsServer, ServerNotification};
+/// # use ancs::transport::loopback::Loopback;
+/// let mut loopback = Loopback::new()
+///     .with_server(AncsServer::new().with_remove_on_negative(true))
+///     .with_mtu(23)
+///     .with_latency(Duration::from_millis(20))
+///     .with_client(|client| client.with_attributes(vec![RequestedAttribute::Message(100)]));
+/// loopback.subscribe();
+///
+/// let message = "A message that needs several Data Source notifications at this MTU";
+/// let uid = loopback.server_mut().add(
+///     ServerNotification::new(CategoryID::Social)
+///         .with_flags(EventFlag::NegativeAction)
+///         .with_attribute(NotificationAttributeID::Message, message.to_string()),
+/// );
+/// loopback.run_until_idle().unwrap();
+///
+/// loopback.assert_attribute(uid, NotificationAttributeID::Message, message);
+/// loopback.assert_synchronized();
+///
+/// // Dismiss the notification, which the provider then removes
+/// loopback.client_mut().perform_notification_action(uid, ActionID::Negative);
+/// loopback.run_until_idle().unwrap();
+/// assert_eq!(loopback.server_mut().poll_action().map(|action| action.notification_uid), Some(uid));
+/// loopback.assert_absent(uid);
+/// // What the server sends in answer to a write takes a round trip, the rest one latency
+/// assert_eq!(loopback.elapsed(), Duration::from_millis(100));
+/// ```
+#[derive(Debug)]
+pub struct Loopback {
+    clock: ManualClock,
+    start: Instant,
+    client: AncsClient<ManualClock>,
+    transport: LoopbackTransport,
+    store: NotificationStore,
+    events: Vec<Event>,
+}
+
+impl Default for Loopback {
+    fn default() -> Self {
+        Loopback::new()
+    }
+}
+
+impl Loopback {
+    /// Creates a `Loopback` with a default `AncsClient` and `AncsServer`, the
+    /// `DEFAULT_ATT_MTU` and no latency.
+    pub fn new() -> Loopback {
+        let clock = ManualClock::new();
+
+        Loopback {
+            start: clock.now(),
+            client: AncsClient::with_clock(clock.clone()),
+            transport: LoopbackTransport::new(AncsServer::new(), clock.clone()),
+            store: NotificationStore::new(),
+            events: Vec::new(),
+            clock,
+        }
+    }
+
+    /// Configures the client, which must keep the harness clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `configure` returns a client reading time from another clock,
+    /// which would never see the harness advance time.
+    ///
+    /// # Examples
+    /// ```should_panic
+    /// # use ancs::client::AncsClient;
+    /// # use ancs::client::clock::ManualClock;
+    /// # use ancs::transport::loopback::Loopback;
+    /// let loopback = Loopback::new().with_client(|_| AncsClient::with_clock(ManualClock::new()));
+    /// ```
+    #[track_caller]
+    pub fn with_client(
+        mut self,
+        configure: impl FnOnce(AncsClient<ManualClock>) -> AncsClient<ManualClock>,
+    ) -> Loopback {
+        self.client = configure(self.client);
+
+        if !self.client.clock().is_shared_with(&self.clock) {
+            panic!("the client does not use the harness clock");
+        }
+
+        self
+    }
+
+    /// Replaces the server, keeping the configured MTU.
+    pub fn with_server(mut self, server: AncsServer) -> Loopback {
+        let mtu = self.transport.server.mtu();
+        self.transport.server = server.with_mtu(mtu);
+        self
+    }
+
+    /// Sets the ATT MTU the server fragments Data Source responses for.
+    pub fn with_mtu(mut self, mtu: u16) -> Loopback {
+        self.transport.server = std::mem::take(&mut self.transport.server).with_mtu(mtu);
+        self
+    }
+
+    /// Sets how long notifications take to arrive, and Control Point writes
+    /// take to reach the server.
+    pub fn with_latency(mut self, latency: Duration) -> Loopback {
+        self.transport.latency = latency;
+        self
+    }
+
+    /// Enables notifications on the Notification Source and Data Source.
+    pub fn subscribe(&mut self) {
+        match transport::subscribe(&mut self.transport) {
+            Ok(()) => (),
+            Err(never) => match never {},
+        }
+    }
+
+    /// Exchanges everything that is due at the current virtual time.
+    pub fn step(&mut self) -> Result<(), Error> {
+        let result = transport::drive(&mut self.client, &mut self.transport);
+
+        while let Some(event) = self.client.poll_event() {
+            self.store.apply_event(&event);
+            self.events.push(event);
+        }
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(TransportError::Protocol(error)) => Err(error),
+            Err(TransportError::Transport(never)) => match never {},
+        }
+    }
+
+    /// Moves the virtual clock forward by `duration`, exchanging everything
+    /// that becomes due on the way.
+    pub fn advance(&mut self, duration: Duration) -> Result<(), Error> {
+        let until = self.clock.now() + duration;
+
+        loop {
+            self.step()?;
+
+            match self.next_wakeup() {
+                Some(at) if at <= until => self.clock.advance_to(at),
+                _ => break,
+            }
+        }
+
+        self.clock.advance_to(until);
+        self.step()
+    }
+
+    /// Runs until nothing is in flight and the client has no command
+    /// outstanding, moving the virtual clock to each delivery or timeout.
+    pub fn run_until_idle(&mut self) -> Result<(), Error> {
+        loop {
+            self.step()?;
+
+            match self.next_wakeup() {
+                Some(at) => self.clock.advance_to(at),
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Returns the virtual time elapsed since the harness was created.
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now() - self.start
+    }
+
+    /// Returns the virtual clock shared by the client and the transport.
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    /// Returns the client.
+    pub fn client(&self) -> &AncsClient<ManualClock> {
+        &self.client
+    }
+
+    /// Returns the client, for example to request attributes or perform actions.
+    pub fn client_mut(&mut self) -> &mut AncsClient<ManualClock> {
+        &mut self.client
+    }
+
+    /// Returns the simulated Notification Provider.
+    pub fn server(&self) -> &AncsServer {
+        &self.transport.server
+    }
+
+    /// Returns the simulated Notification Provider, for example to add notifications.
+    pub fn server_mut(&mut self) -> &mut AncsServer {
+        &mut self.transport.server
+    }
+
+    /// Returns the transport between the client and the server.
+    pub fn transport(&self) -> &LoopbackTransport {
+        &self.transport
+    }
+
+    /// Returns the store the client's events are applied to.
+    pub fn store(&self) -> &NotificationStore {
+        &self.store
+    }
+
+    /// Returns every event the client produced, in order.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Returns the stored notification with the UID, panicking if there is none.
+    #[track_caller]
+    pub fn assert_present(&self, notification_uid: u32) -> &TrackedNotification {
+        match self.store.get(notification_uid) {
+            Some(tracked) => tracked,
+            None => panic!("notification {} is not in the store", notification_uid),
+        }
+    }
+
+    /// Panics if the store holds a notification with the UID.
+    #[track_caller]
+    pub fn assert_absent(&self, notification_uid: u32) {
+        if let Some(tracked) = self.store.get(notification_uid) {
+            panic!("notification {} is still in the store: {:?}", notification_uid, tracked);
+        }
+    }
+
+    /// Panics unless the stored notification with the UID has the attribute value.
+    #[track_caller]
+    pub fn assert_attribute(&self, notification_uid: u32, id: NotificationAttributeID, expected: &str) {
+        let actual = self.assert_present(notification_uid).value(id);
+
+        if actual != Some(expected) {
+            panic!(
+                "notification {} has {:?} {:?}, expected {:?}",
+                notification_uid, id, actual, expected
+            );
+        }
+    }
+
+    /// Panics unless the store holds exactly the notifications the server holds,
+    /// with the same `EventFlag`s.
+    #[track_caller]
+    pub fn assert_synchronized(&self) {
+        for (uid, notification) in self.transport.server.iter() {
+            let tracked = self.assert_present(uid);
+
+            if tracked.flags() != notification.event_flags {
+                panic!(
+                    "notification {} has flags {:?}, the server has {:?}",
+                    uid,
+                    tracked.flags(),
+                    notification.event_flags
+                );
+            }
+        }
+
+        if self.store.len() != self.transport.server.len() {
+            let extra: Vec<u32> = self
+                .store
+                .iter()
+                .map(TrackedNotification::uid)
+                .filter(|uid| self.transport.server.get(*uid).is_none())
+                .collect();
+            panic!("notifications {:?} are in the store but not on the server", extra);
+        }
+    }
+
+    /// Returns when something next becomes due: a delivery or a command timeout.
+    fn next_wakeup(&self) -> Option<Instant> {
+        match (self.transport.next_delivery(), self.client.poll_timeout()) {
+            (Some(delivery), Some(timeout)) => Some(delivery.min(timeout)),
+            (delivery, timeout) => delivery.or(timeout),
+        }
+    }
+}
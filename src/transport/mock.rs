@@ -0,0 +1,197 @@
+//! ## Mock Transport
+//!
+//! An in-memory `GattTransport` for testing ANCS logic without Bluetooth
+//! hardware. The `MockTransport` records every Control Point write, delivers
+//! injected notifications, and can be scripted to answer specific writes on the
+//! Data Source, reject them with a `ControlPointError`, or disconnect.
+//!
+use std::collections::VecDeque;
+
+use uuid::Uuid;
+
+use crate::characteristics::control_point::{ControlPointError, WriteError};
+use crate::characteristics::data_source::DATA_SOURCE_UUID;
+use crate::characteristics::notification_source::{Notification, NOTIFICATION_SOURCE_UUID};
+use crate::transport::GattTransport;
+
+/// The errors reported by a `MockTransport`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MockError {
+    /// The transport was disconnected with `MockTransport::disconnect`.
+    Disconnected,
+    /// Notifications were enabled on a characteristic that cannot notify.
+    UnknownCharacteristic(Uuid),
+}
+
+impl std::fmt::Display for MockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockError::Disconnected => write!(f, "disconnected"),
+            MockError::UnknownCharacteristic(uuid) => write!(f, "characteristic {} cannot notify", uuid),
+        }
+    }
+}
+
+impl std::error::Error for MockError {}
+
+/// The `MockTransport` type. See [the module level documentation](index.html) for more.
+///
+/// Like a real peripheral, notifications are only delivered on characteristics
+/// that have notifications enabled; others are dropped when they come up.
+///
+/// # Examples
+/// ```
+/// # use ancs::characteristics::control_point::{ControlPointError, WriteError};
+/// # use ancs::characteristics::data_source::DATA_SOURCE_UUID;
+/// # use ancs::transport::GattTransport;
+/// # use ancs::transport::mock::{MockError, MockTransport};
+/// let mut transport = MockTransport::new();
+/// transport.enable_notifications(DATA_SOURCE_UUID).unwrap();
+///
+/// transport.respond_to(vec![2, 1, 0, 0, 0, 0], vec![vec![1, 2, 3]]);
+/// transport.reject_next_write(ControlPointError::InvalidParameter);
+///
+/// assert_eq!(transport.write_control_point(&[2, 1, 0, 0, 0, 0]), Err(WriteError::Rejected(ControlPointError::InvalidParameter)));
+/// assert_eq!(transport.receive(), Ok(None));
+///
+/// assert_eq!(transport.write_control_point(&[2, 1, 0, 0, 0, 0]), Ok(()));
+/// assert_eq!(transport.receive(), Ok(Some((DATA_SOURCE_UUID, vec![1, 2, 3]))));
+/// assert_eq!(transport.writes().len(), 2);
+///
+/// transport.disconnect();
+/// assert_eq!(transport.receive(), Err(MockError::Disconnected));
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MockTransport {
+    disconnected: bool,
+    enabled: Vec<Uuid>,
+    writes: Vec<Vec<u8>>,
+    incoming: VecDeque<(Uuid, Vec<u8>)>,
+    write_results: VecDeque<Result<(), WriteError<MockError>>>,
+    /// Data Source replies sent once for the first accepted write matching the request.
+    responses: Vec<(Vec<u8>, Vec<Vec<u8>>)>,
+}
+
+impl MockTransport {
+    /// Creates a connected `MockTransport` without notifications enabled.
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Queues a notification on the given characteristic.
+    pub fn inject(&mut self, characteristic: Uuid, bytes: Vec<u8>) {
+        self.incoming.push_back((characteristic, bytes));
+    }
+
+    /// Queues a Notification Source notification.
+    pub fn inject_notification(&mut self, notification: Notification) {
+        let bytes: [u8; 8] = notification.into();
+        self.inject(NOTIFICATION_SOURCE_UUID, bytes.to_vec());
+    }
+
+    /// Queues a Data Source notification.
+    pub fn inject_data_source(&mut self, bytes: Vec<u8>) {
+        self.inject(DATA_SOURCE_UUID, bytes);
+    }
+
+    /// Answers the next accepted Control Point write of exactly `request` by
+    /// queueing each of `replies` as a Data Source notification.
+    pub fn respond_to(&mut self, request: Vec<u8>, replies: Vec<Vec<u8>>) {
+        self.responses.push((request, replies));
+    }
+
+    /// Makes the next Control Point write fail with the given `ControlPointError`.
+    pub fn reject_next_write(&mut self, error: ControlPointError) {
+        self.write_results.push_back(Err(WriteError::Rejected(error)));
+    }
+
+    /// Makes every operation fail with `MockError::Disconnected` until `reconnect`.
+    pub fn disconnect(&mut self) {
+        self.disconnected = true;
+    }
+
+    /// Reconnects after `disconnect`. Notifications must be enabled again, and
+    /// everything queued before is dropped.
+    pub fn reconnect(&mut self) {
+        self.disconnected = false;
+        self.enabled.clear();
+        self.incoming.clear();
+    }
+
+    /// Determines if notifications are enabled on the given characteristic.
+    pub fn is_enabled(&self, characteristic: Uuid) -> bool {
+        self.enabled.contains(&characteristic)
+    }
+
+    /// Returns the characteristics notifications were enabled on, in order.
+    pub fn enabled(&self) -> &[Uuid] {
+        &self.enabled
+    }
+
+    /// Returns every Control Point write so far, including rejected ones.
+    pub fn writes(&self) -> &[Vec<u8>] {
+        &self.writes
+    }
+
+    /// Returns and forgets every Control Point write so far.
+    pub fn take_writes(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.writes)
+    }
+
+    /// Returns the number of notifications not yet received.
+    pub fn pending(&self) -> usize {
+        self.incoming.len()
+    }
+}
+
+impl GattTransport for MockTransport {
+    type Error = MockError;
+
+    fn write_control_point(&mut self, bytes: &[u8]) -> Result<(), WriteError<MockError>> {
+        if self.disconnected {
+            return Err(WriteError::Transport(MockError::Disconnected));
+        }
+
+        self.writes.push(bytes.to_vec());
+        self.write_results.pop_front().unwrap_or(Ok(()))?;
+
+        if let Some(index) = self.responses.iter().position(|(request, _)| request == bytes) {
+            let (_, replies) = self.responses.remove(index);
+            for reply in replies {
+                self.inject_data_source(reply);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn enable_notifications(&mut self, characteristic: Uuid) -> Result<(), MockError> {
+        if self.disconnected {
+            return Err(MockError::Disconnected);
+        }
+
+        if characteristic != NOTIFICATION_SOURCE_UUID && characteristic != DATA_SOURCE_UUID {
+            return Err(MockError::UnknownCharacteristic(characteristic));
+        }
+
+        if !self.enabled.contains(&characteristic) {
+            self.enabled.push(characteristic);
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<(Uuid, Vec<u8>)>, MockError> {
+        if self.disconnected {
+            return Err(MockError::Disconnected);
+        }
+
+        while let Some((characteristic, bytes)) = self.incoming.pop_front() {
+            if self.enabled.contains(&characteristic) {
+                return Ok(Some((characteristic, bytes)));
+            }
+        }
+
+        Ok(None)
+    }
+}